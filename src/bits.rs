@@ -0,0 +1,361 @@
+use crate::{colex_unrank_into, narrow, wide_binomial, wide_colex_rank};
+use std::iter::FusedIterator;
+
+/// A set of indices stored as bits, which [`BitCombinations`] can produce.
+/// 
+/// Bit `i` is set if index `i` is in the set. This is implemented for `u64` and `u128`, which hold indices below 64 and
+/// 128 respectively, and for [`BitSet`], which holds any indices.
+/// 
+/// [`BitCombinations`]: struct.BitCombinations.html
+/// [`BitSet`]: struct.BitSet.html
+pub trait BitMask: Clone + Eq + private::Sealed {
+    /// The number of distinct indices that fit, or `None` if there is no limit.
+    const CAPACITY: Option<usize>;
+
+    /// Returns the set containing each of `indices`, or `None` if any of them does not fit.
+    fn from_indices(indices: &[usize]) -> Option<Self>;
+
+    /// Returns the indices in the set in increasing order.
+    fn to_indices(&self) -> Vec<usize>;
+
+    #[doc(hidden)]
+    /// Returns the set of the indices `lo..lo + k`.
+    fn run(lo: usize, k: usize) -> Self;
+
+    #[doc(hidden)]
+    /// Moves to the next set with the same number of indices in co-lexicographic order, which must exist.
+    fn step(&mut self);
+}
+
+mod private {
+    pub trait Sealed {}
+
+    impl Sealed for u64 {}
+    impl Sealed for u128 {}
+    impl Sealed for super::BitSet {}
+}
+
+macro_rules! impl_bit_mask {
+    ($t:ty, $bits:expr) => {
+        impl BitMask for $t {
+            const CAPACITY: Option<usize> = Some($bits);
+
+            fn from_indices(indices: &[usize]) -> Option<$t> {
+                indices.iter().try_fold(0, |mask, &i| if i < $bits { Some(mask | 1 << i) } else { None })
+            }
+
+            fn to_indices(&self) -> Vec<usize> {
+                (0..$bits).filter(|i| self & 1 << i != 0).collect()
+            }
+
+            fn run(lo: usize, k: usize) -> $t {
+                if k == 0 {
+                    0
+                } else {
+                    (<$t>::MAX >> ($bits - k)) << lo
+                }
+            }
+
+            fn step(&mut self) {
+                // Gosper's hack: move the lowest run of ones up by one place, keeping all but its top one at the bottom
+                let v = *self;
+                let c = v & v.wrapping_neg();
+                let r = v.wrapping_add(c);
+                *self = (((r ^ v) >> 2) / c) | r;
+            }
+        }
+    };
+}
+
+impl_bit_mask!(u64, 64);
+impl_bit_mask!(u128, 128);
+
+/// A growable set of indices stored as bits, for combinations of more items than fit in a `u128`.
+/// 
+/// Two sets are equal, and hash the same, if and only if they contain the same indices.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct BitSet {
+    words: Vec<u64>, // never has trailing zero words
+}
+
+impl BitSet {
+    /// Creates an empty set.
+    pub fn new() -> BitSet {
+        BitSet { words: Vec::new() }
+    }
+
+    /// Returns whether `index` is in the set.
+    pub fn contains(&self, index: usize) -> bool {
+        self.words.get(index / 64).is_some_and(|word| word & 1 << (index % 64) != 0)
+    }
+
+    /// Adds `index` to the set.
+    pub fn insert(&mut self, index: usize) {
+        if self.words.len() <= index / 64 {
+            self.words.resize(index / 64 + 1, 0);
+        }
+        self.words[index / 64] |= 1 << (index % 64);
+    }
+
+    /// Removes `index` from the set.
+    pub fn remove(&mut self, index: usize) {
+        if let Some(word) = self.words.get_mut(index / 64) {
+            *word &= !(1 << (index % 64));
+            while self.words.last() == Some(&0) {
+                self.words.pop();
+            }
+        }
+    }
+
+    /// Returns the number of indices in the set.
+    pub fn len(&self) -> usize {
+        self.words.iter().map(|word| word.count_ones() as usize).sum()
+    }
+
+    /// Returns whether the set is empty.
+    pub fn is_empty(&self) -> bool {
+        self.words.is_empty()
+    }
+
+    /// Returns the bits of the set, 64 at a time, starting with indices `0..64`.
+    pub fn words(&self) -> &[u64] {
+        &self.words
+    }
+}
+
+impl BitMask for BitSet {
+    const CAPACITY: Option<usize> = None;
+
+    fn from_indices(indices: &[usize]) -> Option<BitSet> {
+        let mut ret = BitSet::new();
+        for &i in indices {
+            ret.insert(i);
+        }
+        Some(ret)
+    }
+
+    fn to_indices(&self) -> Vec<usize> {
+        let mut ret = Vec::new();
+        for (w, &word) in self.words.iter().enumerate() {
+            let mut word = word;
+            while word != 0 {
+                ret.push(w * 64 + word.trailing_zeros() as usize);
+                word &= word - 1;
+            }
+        }
+        ret
+    }
+
+    fn run(lo: usize, k: usize) -> BitSet {
+        let mut ret = BitSet::new();
+        for i in lo..lo + k {
+            ret.insert(i);
+        }
+        ret
+    }
+
+    fn step(&mut self) {
+        // the same as Gosper's hack, one bit at a time
+        let w = self.words.iter().position(|&word| word != 0).unwrap();
+        let lowest = w * 64 + self.words[w].trailing_zeros() as usize;
+        let mut end = lowest;
+        while self.contains(end) {
+            self.remove(end);
+            end += 1;
+        }
+        self.insert(end);
+        for i in 0..end - lowest - 1 {
+            self.insert(i);
+        }
+    }
+}
+
+/// Iterates over all possible combinations of the indices `0..n` as bitmasks.
+/// 
+/// The bitmasks are produced in increasing numeric order, which is co-lexicographic order on the indices: a
+/// combination comes before another if its largest index that they don't share is smaller. Each step takes only a few
+/// instructions for `u64` and `u128`, using Gosper's hack.
+/// 
+/// # Examples
+/// 
+/// ```
+/// use gen_combinations::BitCombinations;
+/// 
+/// let masks: Vec<u64> = BitCombinations::new(4, 2).collect();
+/// assert_eq!(masks, [0b0011, 0b0101, 0b0110, 0b1001, 0b1010, 0b1100]);
+/// ```
+#[derive(Clone, Debug)]
+pub struct BitCombinations<B> {
+    next: Option<B>,
+    last: B,
+    remaining: Option<u128>, // None if it does not fit in a u128
+}
+
+impl<B: BitMask> BitCombinations<B> {
+    /// Creates an iterator over combinations of the indices `0..n` with length `k`.
+    /// 
+    /// If `k` is 0 or greater than `n`, the iterator will produce no values.
+    /// 
+    /// # Panics
+    /// 
+    /// Panics if `n` is greater than [`B::CAPACITY`].
+    /// 
+    /// [`B::CAPACITY`]: trait.BitMask.html#associatedconstant.CAPACITY
+    pub fn new(n: usize, k: usize) -> BitCombinations<B> {
+        assert!(B::CAPACITY.is_none_or(|capacity| n <= capacity), "{} items do not fit in the bitmask", n);
+        if k == 0 || k > n {
+            return BitCombinations { next: None, last: B::run(0, 0), remaining: Some(0) };
+        }
+        BitCombinations { next: Some(B::run(0, k)), last: B::run(n - k, k), remaining: wide_binomial(n, k) }
+    }
+
+    /// Returns the number of combinations left, or `None` if it does not fit in a `usize` and [`len`] would panic.
+    /// 
+    /// [`len`]: https://doc.rust-lang.org/std/iter/trait.ExactSizeIterator.html#method.len
+    pub fn remaining(&self) -> Option<usize> {
+        self.remaining.and_then(narrow)
+    }
+
+    /// Returns the co-lexicographic rank of `mask` among the bitmasks with the same number of bits set, which is also
+    /// its position in a `BitCombinations` over any `n` that it fits in.
+    /// 
+    /// Returns `None` if the rank does not fit in a `usize`.
+    /// 
+    /// # Examples
+    /// 
+    /// ```
+    /// use gen_combinations::BitCombinations;
+    /// 
+    /// assert_eq!(BitCombinations::rank(&0b1010u64), Some(4));
+    /// ```
+    pub fn rank(mask: &B) -> Option<usize> {
+        narrow(wide_colex_rank(&mask.to_indices())?)
+    }
+
+    /// Returns the bitmask with `k` bits set that has the given co-lexicographic `rank`.
+    /// 
+    /// This is the inverse of [`rank`]. Returns `None` if the bitmask does not fit in `B`, or if `k` is 0 and `rank` is
+    /// not, since the empty combination is the only one with no bits set.
+    /// 
+    /// # Examples
+    /// 
+    /// ```
+    /// use gen_combinations::BitCombinations;
+    /// 
+    /// assert_eq!(BitCombinations::unrank(2, 4), Some(0b1010u64));
+    /// ```
+    /// 
+    /// [`rank`]: #method.rank
+    pub fn unrank(k: usize, rank: usize) -> Option<B> {
+        if k == 0 && rank > 0 {
+            return None;
+        }
+        let mut indices = vec![0; k];
+        colex_unrank_into(&mut indices, rank as u128);
+        B::from_indices(&indices)
+    }
+}
+
+impl<B: BitMask> Iterator for BitCombinations<B> {
+    type Item = B;
+
+    fn next(&mut self) -> Option<B> {
+        let ret = self.next.take()?;
+        if ret != self.last {
+            let mut next = ret.clone();
+            next.step();
+            self.next = Some(next);
+        }
+        self.remaining = self.remaining.map(|remaining| remaining - 1);
+        Some(ret)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        match self.remaining.and_then(narrow) {
+            Some(remaining) => (remaining, Some(remaining)),
+            None => (usize::MAX, None),
+        }
+    }
+
+    fn nth(&mut self, n: usize) -> Option<B> {
+        if n > 0 {
+            let current = self.next.as_ref()?.to_indices();
+            match (wide_colex_rank(&current), self.remaining) {
+                (Some(_), Some(remaining)) if n as u128 >= remaining => {
+                    self.next = None;
+                    self.remaining = Some(0);
+                    return None;
+                }
+                (Some(rank), remaining) => {
+                    let mut indices = current;
+                    colex_unrank_into(&mut indices, rank + n as u128);
+                    self.next = B::from_indices(&indices);
+                    self.remaining = remaining.map(|remaining| remaining - n as u128);
+                }
+                _ => {
+                    for _ in 0..n {
+                        self.next()?;
+                    }
+                }
+            }
+        }
+        self.next()
+    }
+}
+
+impl<B: BitMask> ExactSizeIterator for BitCombinations<B> {}
+
+impl<B: BitMask> FusedIterator for BitCombinations<B> {}
+
+#[test]
+fn gosper_matches_bitset() {
+    for n in 0..10 {
+        for k in 0..=n {
+            let small: Vec<Vec<usize>> = BitCombinations::<u64>::new(n, k).map(|mask| mask.to_indices()).collect();
+            let wide: Vec<Vec<usize>> = BitCombinations::<u128>::new(n, k).map(|mask| mask.to_indices()).collect();
+            let set: Vec<Vec<usize>> = BitCombinations::<BitSet>::new(n, k).map(|set| set.to_indices()).collect();
+            assert_eq!(small, wide);
+            assert_eq!(small, set);
+            for (rank, indices) in small.iter().enumerate() {
+                let mask = u64::from_indices(indices).unwrap();
+                assert_eq!(BitCombinations::rank(&mask), Some(rank));
+                assert_eq!(BitCombinations::unrank(k, rank), Some(mask));
+                assert_eq!(BitCombinations::<BitSet>::unrank(k, rank), BitSet::from_indices(indices));
+            }
+        }
+    }
+}
+
+#[test]
+fn full_width() {
+    let mut c = BitCombinations::<u64>::new(64, 63);
+    assert_eq!(c.len(), 64);
+    assert_eq!(c.nth(62), Some(u64::MAX - 2));
+    assert_eq!(c.next(), Some(u64::MAX - 1));
+    assert_eq!(c.next(), None);
+
+    let mut c = BitCombinations::<u128>::new(128, 64);
+    assert_eq!(c.size_hint(), (usize::MAX, None));
+    assert_eq!(c.next(), Some(u128::MAX >> 64));
+    assert_eq!(c.nth(1), Some((u128::MAX >> 66) | 1 << 63 | 1 << 64));
+    assert_eq!(BitCombinations::<u128>::unrank(2, 128 * 127 / 2), None);
+    assert_eq!(BitCombinations::<u64>::unrank(0, 0), Some(0));
+    assert_eq!(BitCombinations::<u64>::unrank(0, 5), None);
+}
+
+#[test]
+fn bitset() {
+    let mut set = BitSet::new();
+    set.insert(200);
+    set.insert(3);
+    assert_eq!(set.len(), 2);
+    assert_eq!(set.to_indices(), [3, 200]);
+    set.remove(200);
+    assert_eq!(set, BitSet::from_indices(&[3]).unwrap());
+    assert_eq!(set.words(), &[8]);
+
+    let mut c = BitCombinations::<BitSet>::new(1000, 3);
+    assert_eq!(c.len(), 1000 * 999 * 998 / 6);
+    assert_eq!(c.nth(1000 * 999 * 998 / 6 - 1), BitSet::from_indices(&[997, 998, 999]));
+    assert_eq!(c.next(), None);
+}
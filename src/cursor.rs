@@ -0,0 +1,433 @@
+use crate::remaining::Remaining;
+use crate::{
+    binomial, colex_unrank_into, unrank_from_end, unrank_into, wide_binomial, wide_colex_rank, wide_rank, Order,
+};
+
+/// The position of an iteration over the `k`-combinations of `n` indices in either order, shared by the iterators over
+/// combinations.
+/// 
+/// The iteration runs from `front` to `back` inclusive, and both ends have yet to be produced.
+#[derive(Clone, Debug)]
+pub(crate) struct Cursor {
+    n: usize,
+    k: usize,
+    front: Vec<usize>,
+    back: Vec<usize>,
+    remaining: Remaining,
+    order: Order,
+}
+
+impl Cursor {
+    /// Creates a cursor over the combinations of `k` out of `n` indices in lexicographic order.
+    /// 
+    /// If `k` is 0 or greater than `n`, the cursor will be empty.
+    pub(crate) fn new(n: usize, k: usize) -> Cursor {
+        Cursor::starting_at(n, (0..k).collect(), Order::Lexicographic)
+    }
+
+    /// Creates a cursor from `front` to the last combination of `front.len()` out of `n` indices in the given order.
+    /// 
+    /// If `front` is empty or too long, the cursor will be empty.
+    pub(crate) fn starting_at(n: usize, front: Vec<usize>, order: Order) -> Cursor {
+        let k = front.len();
+        if k == 0 || k > n {
+            return Cursor::empty(n, k, order);
+        }
+        // the first and last combinations are the same in both orders
+        let back: Vec<usize> = (n - k..n).collect();
+        let mut ret = Cursor { n, k, front, back, remaining: Remaining::zero(), order };
+        ret.remaining = Remaining::new(ret.count());
+        ret
+    }
+
+    /// Creates a cursor from `front` to `back` inclusive in the given order, which must be combinations of the same
+    /// length out of `n` indices.
+    /// 
+    /// If `back` comes before `front`, the cursor will be empty.
+    pub(crate) fn between(n: usize, front: Vec<usize>, back: Vec<usize>, order: Order) -> Cursor {
+        let k = front.len();
+        let backwards = match order {
+            Order::Lexicographic => front > back,
+            Order::Colexicographic => front.iter().rev().gt(back.iter().rev()),
+        };
+        if k == 0 || backwards {
+            return Cursor::empty(n, k, order);
+        }
+        let mut ret = Cursor { n, k, front, back, remaining: Remaining::zero(), order };
+        ret.remaining = Remaining::new(ret.count());
+        ret
+    }
+
+    /// Creates an empty cursor over the combinations of `k` out of `n` indices.
+    pub(crate) fn empty(n: usize, k: usize, order: Order) -> Cursor {
+        Cursor { n, k, front: Vec::new(), back: Vec::new(), remaining: Remaining::zero(), order }
+    }
+
+    /// Returns the length of the combinations, even once there are none left.
+    pub(crate) fn k(&self) -> usize {
+        self.k
+    }
+
+    pub(crate) fn order(&self) -> Order {
+        self.order
+    }
+
+    /// Returns the next combination from the front, or an empty slice if there are none left.
+    pub(crate) fn front(&self) -> &[usize] {
+        &self.front
+    }
+
+    /// Returns the next combination from the back, or an empty slice if there are none left.
+    pub(crate) fn back(&self) -> &[usize] {
+        &self.back
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.remaining.is_zero()
+    }
+
+    /// Returns the number of combinations left, or `None` if it does not fit in a `usize`.
+    pub(crate) fn remaining(&self) -> Option<usize> {
+        self.remaining.get()
+    }
+
+    pub(crate) fn size_hint(&self) -> (usize, Option<usize>) {
+        self.remaining.size_hint()
+    }
+
+    /// Moves the front past the current combination, which must exist.
+    pub(crate) fn step_front(&mut self) {
+        if self.remaining.is_one() {
+            // the front has caught up with the back, and there may be no combination after it
+            self.exhaust();
+        } else {
+            match self.order {
+                Order::Lexicographic => successor(&mut self.front, self.n),
+                Order::Colexicographic => colex_successor(&mut self.front, self.n),
+            };
+            self.consume(1);
+        }
+    }
+
+    /// Moves the back past the current combination, which must exist.
+    pub(crate) fn step_back(&mut self) {
+        if self.remaining.is_one() {
+            self.exhaust();
+        } else {
+            match self.order {
+                Order::Lexicographic => predecessor(&mut self.back, self.n),
+                Order::Colexicographic => colex_predecessor(&mut self.back),
+            };
+            self.consume(1);
+        }
+    }
+
+    /// Moves the front forward by `by` combinations without producing them.
+    /// 
+    /// Returns `false`, leaving the cursor empty, if there are not more than `by` combinations left.
+    pub(crate) fn skip_front(&mut self, by: usize) -> bool {
+        if !self.remaining.exceeds(by) {
+            self.exhaust();
+            return false;
+        }
+        if by > 0 {
+            match self.order {
+                Order::Lexicographic => advance_by(&mut self.front, self.n, by),
+                Order::Colexicographic => colex_advance_by(&mut self.front, self.n, by),
+            };
+            self.consume(by);
+        }
+        true
+    }
+
+    /// Moves the back backward by `by` combinations without producing them.
+    /// 
+    /// Returns `false`, leaving the cursor empty, if there are not more than `by` combinations left.
+    pub(crate) fn skip_back(&mut self, by: usize) -> bool {
+        if !self.remaining.exceeds(by) {
+            self.exhaust();
+            return false;
+        }
+        if by > 0 {
+            match self.order {
+                Order::Lexicographic => retreat_by(&mut self.back, self.n, by),
+                Order::Colexicographic => colex_retreat_by(&mut self.back, self.n, by),
+            };
+            self.consume(by);
+        }
+        true
+    }
+
+    /// Passes the next combination from the front to `f` and moves the front past it, or returns `None` if there are
+    /// none left.
+    pub(crate) fn next_front<R, F: FnOnce(&[usize]) -> R>(&mut self, f: F) -> Option<R> {
+        if self.is_empty() {
+            return None;
+        }
+        let ret = f(&self.front);
+        self.step_front();
+        Some(ret)
+    }
+
+    /// Passes the next combination from the back to `f` and moves the back past it, or returns `None` if there are
+    /// none left.
+    pub(crate) fn next_back<R, F: FnOnce(&[usize]) -> R>(&mut self, f: F) -> Option<R> {
+        if self.is_empty() {
+            return None;
+        }
+        let ret = f(&self.back);
+        self.step_back();
+        Some(ret)
+    }
+
+    /// Skips `n` combinations from the front, then does as [`next_front`](Cursor::next_front).
+    pub(crate) fn nth_front<R, F: FnOnce(&[usize]) -> R>(&mut self, n: usize, f: F) -> Option<R> {
+        if self.skip_front(n) {
+            self.next_front(f)
+        } else {
+            None
+        }
+    }
+
+    /// Skips `n` combinations from the back, then does as [`next_back`](Cursor::next_back).
+    pub(crate) fn nth_back<R, F: FnOnce(&[usize]) -> R>(&mut self, n: usize, f: F) -> Option<R> {
+        if self.skip_back(n) {
+            self.next_back(f)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the number of combinations left, for [`Iterator::count`].
+    /// 
+    /// # Panics
+    /// 
+    /// Panics if there are more than `usize::MAX` combinations left.
+    pub(crate) fn len(&self) -> usize {
+        self.remaining().expect("more than usize::MAX combinations")
+    }
+
+    /// Updates the remaining count after moving either end inward by `by` combinations.
+    fn consume(&mut self, by: usize) {
+        self.remaining = self.remaining.consumed(by, || self.count());
+    }
+
+    /// Counts the combinations from the front to the back, or returns `None` if there are more than `u128::MAX`.
+    fn count(&self) -> Option<u128> {
+        match self.order {
+            Order::Lexicographic => count_between(self.n, &self.front, &self.back),
+            Order::Colexicographic => {
+                // reflecting reverses the order, and the lexicographic count works even where the ranks do not fit
+                count_between(self.n, &reflect(&self.back, self.n), &reflect(&self.front, self.n))
+            }
+        }
+    }
+
+    fn exhaust(&mut self) {
+        self.front.clear();
+        self.back.clear();
+        self.remaining = Remaining::zero();
+    }
+}
+
+/// Returns the number of combinations out of `n` items from `front` to `back` inclusive in lexicographic order, or
+/// `None` if it does not fit in a `u128`.
+fn count_between(n: usize, front: &[usize], back: &[usize]) -> Option<u128> {
+    // near the first combination the ranks fit, and near the last the counts of combinations after each end do
+    if let (Some(front), Some(back)) = (wide_rank(n, front), wide_rank(n, back)) {
+        return Some(back - front + 1);
+    }
+    // the number of combinations after `indices`
+    let after = |indices: &[usize]| {
+        let k = indices.len();
+        indices.iter().enumerate().try_fold(0u128, |count, (i, &c)| count.checked_add(wide_binomial(n - 1 - c, k - i)?))
+    };
+    Some(after(front)? - after(back)? + 1)
+}
+
+/// Moves the combination in `indices` (out of `n` items) to the next one in lexicographic order.
+/// 
+/// Returns `false`, leaving `indices` unchanged, if it is already the last combination.
+pub(crate) fn successor(indices: &mut [usize], n: usize) -> bool {
+    for i in (0..indices.len()).rev() {
+        if indices[i] < n - (indices.len() - i) {
+            indices[i] += 1;
+            for j in i..indices.len() {
+                indices[j] = indices[i] + (j - i);
+            }
+            return true;
+        }
+    }
+    false
+}
+
+/// Moves the combination in `indices` (out of `n` items) to the previous one in lexicographic order.
+/// 
+/// Returns `false`, leaving `indices` unchanged, if it is already the first combination.
+pub(crate) fn predecessor(indices: &mut [usize], n: usize) -> bool {
+    for i in (0..indices.len()).rev() {
+        let lo = if i == 0 { 0 } else { indices[i - 1] + 1 };
+        if indices[i] > lo {
+            indices[i] -= 1;
+            for j in i + 1..indices.len() {
+                indices[j] = n - (indices.len() - j);
+            }
+            return true;
+        }
+    }
+    false
+}
+
+/// Moves the combination in `indices` (out of `n` items) forward by `by` places in lexicographic order.
+/// 
+/// Returns `false`, leaving `indices` unchanged, if there are not that many combinations after it.
+fn advance_by(indices: &mut [usize], n: usize, by: usize) -> bool {
+    let k = indices.len();
+    let mut by = by;
+    // the combinations after `indices` come in groups: first those that only differ in the last position, then those
+    // that first differ in the second-to-last position, and so on
+    for i in (0..k).rev() {
+        let lo = indices[i] + 1;
+        match binomial(n - lo, k - i) {
+            Some(group) if by > group => by -= group,
+            _ => {
+                unrank_into(&mut indices[i..], lo, n, (by - 1) as u128);
+                return true;
+            }
+        }
+    }
+    false
+}
+
+/// Moves the combination in `indices` (out of `n` items) back by `by` places in lexicographic order.
+/// 
+/// Returns `false`, leaving `indices` unchanged, if there are not that many combinations before it.
+fn retreat_by(indices: &mut [usize], n: usize, by: usize) -> bool {
+    let k = indices.len();
+    let mut by = by as u128;
+    // the combinations before `indices` come in groups like in `advance_by`
+    for i in (0..k).rev() {
+        let lo = if i == 0 { 0 } else { indices[i - 1] + 1 };
+        let c = indices[i];
+        if c == lo {
+            continue;
+        }
+        let j = k - i;
+        let after = match wide_binomial(n - c, j) {
+            Some(after) => after,
+            None => {
+                // too many to count, but then the block with `c - 1` at position i alone is more than `by`
+                indices[i] = c - 1;
+                unrank_from_end(&mut indices[i + 1..], c, n, (by - 1) as usize);
+                return true;
+            }
+        };
+        // the number of combinations in the group from the one with `x` at position i
+        let from = |x: usize| wide_binomial(n - x, j).map(|count| count - after);
+        match from(lo) {
+            Some(group) if by > group => by -= group,
+            _ => {
+                let (mut a, mut b) = (lo, c - 1);
+                while a < b {
+                    let mid = b - (b - a) / 2;
+                    if from(mid).is_none_or(|count| count >= by) {
+                        a = mid;
+                    } else {
+                        b = mid - 1;
+                    }
+                }
+                indices[i] = a;
+                let skipped = from(a + 1).unwrap();
+                unrank_from_end(&mut indices[i + 1..], a + 1, n, (by - skipped - 1) as usize);
+                return true;
+            }
+        }
+    }
+    false
+}
+
+/// Moves the combination in `indices` (out of `n` items) to the next one in co-lexicographic order.
+/// 
+/// Returns `false`, leaving `indices` unchanged, if it is already the last combination.
+fn colex_successor(indices: &mut [usize], n: usize) -> bool {
+    for i in 0..indices.len() {
+        let limit = if i + 1 < indices.len() { indices[i + 1] } else { n };
+        if indices[i] + 1 < limit {
+            indices[i] += 1;
+            for (j, index) in indices[..i].iter_mut().enumerate() {
+                *index = j;
+            }
+            return true;
+        }
+    }
+    false
+}
+
+/// Moves the combination in `indices` to the previous one in co-lexicographic order.
+/// 
+/// Returns `false`, leaving `indices` unchanged, if it is already the first combination.
+fn colex_predecessor(indices: &mut [usize]) -> bool {
+    for i in 0..indices.len() {
+        if indices[i] > i {
+            indices[i] -= 1;
+            for j in 0..i {
+                indices[j] = indices[i] - (i - j);
+            }
+            return true;
+        }
+    }
+    false
+}
+
+/// Moves the combination in `indices` (out of `n` items) forward by `by` places in co-lexicographic order.
+/// 
+/// Returns `false`, leaving `indices` unchanged, if there are not that many combinations after it.
+fn colex_advance_by(indices: &mut [usize], n: usize, by: usize) -> bool {
+    let target = wide_colex_rank(indices).and_then(|rank| rank.checked_add(by as u128));
+    match target {
+        Some(target) if wide_binomial(n, indices.len()).is_none_or(|count| target < count) => {
+            colex_unrank_into(indices, target);
+            true
+        }
+        Some(_) => false,
+        // too far along to rank, but moving forward here is moving backward from the reflection in lexicographic order
+        None => {
+            let mut reflected = reflect(indices, n);
+            if !retreat_by(&mut reflected, n, by) {
+                return false;
+            }
+            indices.copy_from_slice(&reflect(&reflected, n));
+            true
+        }
+    }
+}
+
+/// Moves the combination in `indices` (out of `n` items) back by `by` places in co-lexicographic order.
+/// 
+/// Returns `false`, leaving `indices` unchanged, if there are not that many combinations before it.
+fn colex_retreat_by(indices: &mut [usize], n: usize, by: usize) -> bool {
+    match wide_colex_rank(indices) {
+        Some(rank) if rank < by as u128 => false,
+        Some(rank) => {
+            colex_unrank_into(indices, rank - by as u128);
+            true
+        }
+        // likewise, moving backward is moving forward from the reflection
+        None => {
+            let mut reflected = reflect(indices, n);
+            if !advance_by(&mut reflected, n, by) {
+                return false;
+            }
+            indices.copy_from_slice(&reflect(&reflected, n));
+            true
+        }
+    }
+}
+
+/// Reflects a combination out of `n` items, replacing each index `i` with `n - 1 - i`.
+/// 
+/// This turns co-lexicographic order into reverse lexicographic order, so the last combination in one is the first in
+/// the other.
+fn reflect(indices: &[usize], n: usize) -> Vec<usize> {
+    indices.iter().rev().map(|&i| n - 1 - i).collect()
+}
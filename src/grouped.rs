@@ -0,0 +1,397 @@
+use crate::cursor::successor;
+use crate::remaining::{Ranked, Remaining};
+use crate::{narrow, unrank_into, wide_binomial, wide_rank};
+use std::iter::FusedIterator;
+
+/// Iterates over all possible ways to choose a combination from each of several groups of items.
+/// 
+/// Each group is a slice along with the number of items to choose from it. The selections are produced as a single
+/// `Vec` holding the items chosen from the first group, then those from the second, and so on. They are in
+/// lexicographic order of their indices, so the choice from the last group changes fastest, and there are
+/// [`count_grouped`] of them.
+/// 
+/// The choices are advanced in place, so apart from the selection itself nothing is allocated at each step, and
+/// nothing at all by [`next_slice`] and [`next_into`].
+/// 
+/// If there are no groups, or any group has a length of 0 or greater than its number of items, the iterator will
+/// produce no values.
+/// 
+/// # Examples
+/// 
+/// ```
+/// use gen_combinations::GroupedCombinations;
+/// 
+/// let frontend = ["Ada", "Brian", "Claude"];
+/// let backend = ["Dennis", "Edsger"];
+/// let qa = ["Frances", "Grace", "Hedy", "Ivan"];
+/// let groups = [(&frontend[..], 2), (&backend[..], 1), (&qa[..], 3)];
+/// let teams = GroupedCombinations::new(&groups);
+/// assert_eq!(teams.len(), 3 * 2 * 4);
+/// for team in teams {
+///     println!("{:?}", team);
+///     // ["Ada", "Brian", "Dennis", "Frances", "Grace", "Hedy"]
+///     // ["Ada", "Brian", "Dennis", "Frances", "Grace", "Ivan"]
+///     // ...
+/// }
+/// ```
+/// 
+/// [`count_grouped`]: fn.count_grouped.html
+/// [`next_slice`]: #method.next_slice
+/// [`next_into`]: #method.next_into
+#[derive(Debug)]
+pub struct GroupedCombinations<'a, T> {
+    groups: &'a [(&'a [T], usize)],
+    front: Vec<usize>, // the indices of the next selection, into each group in turn
+    remaining: Remaining,
+    buffer: Vec<&'a T>, // reused by next_slice
+}
+
+impl<'a, T> GroupedCombinations<'a, T> {
+    /// Creates an iterator over the ways to choose `k` items from each `(items, k)` group.
+    pub fn new(groups: &'a [(&'a [T], usize)]) -> GroupedCombinations<'a, T> {
+        let sizes = sizes(groups);
+        let remaining = if valid(&sizes) { Remaining::new(wide_count(&sizes)) } else { Remaining::zero() };
+        let front = if remaining.is_zero() { Vec::new() } else { sizes.iter().flat_map(|&(_, k)| 0..k).collect() };
+        GroupedCombinations { groups, front, remaining, buffer: Vec::new() }
+    }
+
+    /// Creates an iterator over the ways to choose `k` items from each `(items, k)` group, starting at the selection
+    /// with the given `rank` (see [`rank_grouped`]).
+    /// 
+    /// If `rank` is past the last selection, the iterator will produce no values.
+    /// 
+    /// [`rank_grouped`]: fn.rank_grouped.html
+    pub fn from_rank(groups: &'a [(&'a [T], usize)], rank: usize) -> GroupedCombinations<'a, T> {
+        let mut ret = GroupedCombinations::new(groups);
+        if rank > 0 {
+            ret.nth(rank - 1);
+        }
+        ret
+    }
+
+    /// Returns the number of selections left, or `None` if it does not fit in a `usize` and [`len`] would panic.
+    /// 
+    /// [`len`]: https://doc.rust-lang.org/std/iter/trait.ExactSizeIterator.html#method.len
+    pub fn remaining(&self) -> Option<usize> {
+        self.remaining.get()
+    }
+
+    /// Returns the indices of the next selection, or an empty slice if there are none left.
+    /// 
+    /// The first `k` indices are into the first group's items, the next into the second's, and so on.
+    pub fn indices(&self) -> &[usize] {
+        &self.front
+    }
+
+    /// Advances the iterator and returns the next selection, like [`next`], but in a buffer owned by the iterator
+    /// instead of a newly allocated `Vec`.
+    /// 
+    /// [`next`]: https://doc.rust-lang.org/std/iter/trait.Iterator.html#tymethod.next
+    pub fn next_slice(&mut self) -> Option<&[&'a T]> {
+        let mut buffer = std::mem::take(&mut self.buffer);
+        let more = self.next_into(&mut buffer);
+        self.buffer = buffer;
+        if more {
+            Some(&self.buffer)
+        } else {
+            None
+        }
+    }
+
+    /// Advances the iterator and writes the next selection into `selection`, replacing its contents.
+    /// 
+    /// Returns `false`, leaving `selection` unchanged, if there are no selections left.
+    pub fn next_into(&mut self, selection: &mut Vec<&'a T>) -> bool {
+        if self.remaining.is_zero() {
+            return false;
+        }
+        selection.clear();
+        selection.extend(self.gather());
+        self.step();
+        true
+    }
+
+    /// Returns the items of the next selection, which must exist.
+    fn gather(&self) -> impl Iterator<Item = &'a T> + '_ {
+        let items = self.groups.iter().flat_map(|&(items, k)| std::iter::repeat_n(items, k));
+        self.front.iter().zip(items).map(|(&i, items)| &items[i])
+    }
+
+    fn count_remaining(&self) -> Option<u128> {
+        let sizes = sizes(self.groups);
+        Some(wide_count(&sizes)? - wide_grouped_rank(&sizes, &self.front)?)
+    }
+}
+
+impl<T> Ranked for GroupedCombinations<'_, T> {
+    fn left(&self) -> Remaining {
+        self.remaining
+    }
+
+    fn rank(&self) -> Option<u128> {
+        wide_grouped_rank(&sizes(self.groups), &self.front)
+    }
+
+    fn jump(&mut self, rank: u128, by: usize) {
+        grouped_unrank_into(&sizes(self.groups), &mut self.front, rank);
+        self.remaining = self.remaining.consumed(by, || self.count_remaining());
+    }
+
+    fn step(&mut self) {
+        if self.remaining.is_one() {
+            self.exhaust();
+            return;
+        }
+        grouped_successor(self.groups.iter().map(|&(items, k)| (items.len(), k)), &mut self.front);
+        self.remaining = self.remaining.consumed(1, || self.count_remaining());
+    }
+
+    fn exhaust(&mut self) {
+        self.front.clear();
+        self.remaining = Remaining::zero();
+    }
+}
+
+impl<T> Clone for GroupedCombinations<'_, T> {
+    fn clone(&self) -> Self {
+        GroupedCombinations {
+            groups: self.groups,
+            front: self.front.clone(),
+            remaining: self.remaining,
+            buffer: Vec::new(),
+        }
+    }
+}
+
+impl<'a, T> Iterator for GroupedCombinations<'a, T> {
+    type Item = Vec<&'a T>;
+
+    fn next(&mut self) -> Option<Vec<&'a T>> {
+        if self.remaining.is_zero() {
+            return None;
+        }
+        let ret = self.gather().collect();
+        self.step();
+        Some(ret)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.remaining.size_hint()
+    }
+
+    fn count(self) -> usize {
+        self.remaining.get().expect("more than usize::MAX selections")
+    }
+
+    fn nth(&mut self, n: usize) -> Option<Vec<&'a T>> {
+        if self.skip_ahead(n) {
+            self.next()
+        } else {
+            None
+        }
+    }
+}
+
+impl<T> ExactSizeIterator for GroupedCombinations<'_, T> {}
+
+impl<T> FusedIterator for GroupedCombinations<'_, T> {}
+
+/// Returns the number of ways to choose `k` out of `n` items from each `(n, k)` group, which is the number of
+/// selections a [`GroupedCombinations`] produces, or `None` if it does not fit in a `usize`.
+/// 
+/// This is the product of the binomial coefficients `C(n, k)`, except that, as for the iterator, it is 0 if there are
+/// no groups or any `k` is 0.
+/// 
+/// # Examples
+/// 
+/// ```
+/// assert_eq!(gen_combinations::count_grouped(&[(3, 2), (2, 1), (4, 3)]), Some(24));
+/// assert_eq!(gen_combinations::count_grouped(&[(3, 2), (2, 0)]), Some(0));
+/// ```
+/// 
+/// [`GroupedCombinations`]: struct.GroupedCombinations.html
+pub fn count_grouped(groups: &[(usize, usize)]) -> Option<usize> {
+    if !valid(groups) {
+        return Some(0);
+    }
+    narrow(wide_count(groups)?)
+}
+
+/// Returns the rank of a selection from the `(n, k)` groups, given as its indices into each group in turn.
+/// 
+/// The first selection, which takes the first `k` items of each group, has rank 0. Returns `None` if `indices` is not
+/// a selection from `groups`, that is if it does not have `k` strictly increasing indices less than `n` for each
+/// group, or if the rank does not fit in a `usize`.
+/// 
+/// # Examples
+/// 
+/// ```
+/// assert_eq!(gen_combinations::rank_grouped(&[(3, 2), (4, 1)], &[0, 2, 3]), Some(7));
+/// assert_eq!(gen_combinations::rank_grouped(&[(3, 2), (4, 1)], &[2, 0, 3]), None);
+/// ```
+pub fn rank_grouped(groups: &[(usize, usize)], indices: &[usize]) -> Option<usize> {
+    if !valid(groups) || indices.len() != groups.iter().map(|&(_, k)| k).sum::<usize>() {
+        return None;
+    }
+    let mut rest = indices;
+    for &(n, k) in groups {
+        let (choice, tail) = rest.split_at(k);
+        if choice.windows(2).any(|w| w[0] >= w[1]) || choice[k - 1] >= n {
+            return None;
+        }
+        rest = tail;
+    }
+    narrow(wide_grouped_rank(groups, indices)?)
+}
+
+/// Returns the indices of the selection from the `(n, k)` groups with the given `rank`.
+/// 
+/// This is the inverse of [`rank_grouped`]. Returns `None` if there are not more than `rank` such selections.
+/// 
+/// # Examples
+/// 
+/// ```
+/// assert_eq!(gen_combinations::unrank_grouped(&[(3, 2), (4, 1)], 7), Some(vec![0, 2, 3]));
+/// assert_eq!(gen_combinations::unrank_grouped(&[(3, 2), (4, 1)], 12), None);
+/// ```
+/// 
+/// [`rank_grouped`]: fn.rank_grouped.html
+pub fn unrank_grouped(groups: &[(usize, usize)], rank: usize) -> Option<Vec<usize>> {
+    if count_grouped(groups).is_some_and(|count| rank >= count) {
+        return None;
+    }
+    let mut indices = vec![0; groups.iter().map(|&(_, k)| k).sum()];
+    grouped_unrank_into(groups, &mut indices, rank as u128);
+    Some(indices)
+}
+
+fn sizes<T>(groups: &[(&[T], usize)]) -> Vec<(usize, usize)> {
+    groups.iter().map(|&(items, k)| (items.len(), k)).collect()
+}
+
+/// Returns whether there are any selections from the `(n, k)` groups.
+fn valid(groups: &[(usize, usize)]) -> bool {
+    !groups.is_empty() && groups.iter().all(|&(n, k)| k > 0 && k <= n)
+}
+
+/// Returns the number of selections from the `(n, k)` groups, or `None` if it does not fit in a `u128`.
+pub(crate) fn wide_count(groups: &[(usize, usize)]) -> Option<u128> {
+    groups.iter().try_fold(1u128, |count, &(n, k)| count.checked_mul(wide_binomial(n, k)?))
+}
+
+/// Returns the rank of a selection, or `None` if it does not fit in a `u128`.
+pub(crate) fn wide_grouped_rank(groups: &[(usize, usize)], indices: &[usize]) -> Option<u128> {
+    // each group's choice is a digit in base C(n, k), and while the rank is still 0 that base need not be computed
+    let mut rank = 0u128;
+    let mut rest = indices;
+    for &(n, k) in groups {
+        let (choice, tail) = rest.split_at(k);
+        if rank > 0 {
+            rank = rank.checked_mul(wide_binomial(n, k)?)?;
+        }
+        rank = rank.checked_add(wide_rank(n, choice)?)?;
+        rest = tail;
+    }
+    Some(rank)
+}
+
+/// Moves the selection in `indices` from the `(n, k)` groups to the next one in lexicographic order.
+/// 
+/// Returns `false`, leaving `indices` back at the first selection, if it is already the last one.
+pub(crate) fn grouped_successor<I>(groups: I, indices: &mut [usize]) -> bool
+where
+    I: DoubleEndedIterator<Item = (usize, usize)>,
+{
+    let mut end = indices.len();
+    for (n, k) in groups.rev() {
+        let choice = &mut indices[end - k..end];
+        if successor(choice, n) {
+            return true;
+        }
+        // the last group that can move carries into the one before it, resetting those after it
+        for (j, slot) in choice.iter_mut().enumerate() {
+            *slot = j;
+        }
+        end -= k;
+    }
+    false
+}
+
+/// Fills `indices` with the selection with the given rank, which must be less than the number of selections.
+pub(crate) fn grouped_unrank_into(groups: &[(usize, usize)], indices: &mut [usize], mut rank: u128) {
+    let mut end = indices.len();
+    for (g, &(n, k)) in groups.iter().enumerate().rev() {
+        // the first group takes whatever is left, so its count is never needed and may be too large to compute
+        // likewise, a count too large for a u128 is more than any rank, so the digit is all of it
+        let digit = match wide_binomial(n, k) {
+            Some(count) if g > 0 => {
+                let digit = rank % count;
+                rank /= count;
+                digit
+            }
+            _ => std::mem::take(&mut rank),
+        };
+        unrank_into(&mut indices[end - k..end], 0, n, digit);
+        end -= k;
+    }
+}
+
+#[test]
+fn grouped_matches_nested() {
+    use crate::CombinationIterator;
+
+    let a = [1, 2, 3, 4];
+    let b = [5, 6, 7];
+    let c = [8, 9];
+    let groups = [(&a[..], 2), (&b[..], 2), (&c[..], 1)];
+    let mut expected = Vec::new();
+    for x in CombinationIterator::new(&a, 2) {
+        for y in CombinationIterator::new(&b, 2) {
+            for z in CombinationIterator::new(&c, 1) {
+                expected.push([&x[..], &y[..], &z[..]].concat());
+            }
+        }
+    }
+    let sizes = [(4, 2), (3, 2), (2, 1)];
+    assert_eq!(count_grouped(&sizes), Some(expected.len()));
+    let mut g = GroupedCombinations::new(&groups);
+    for (r, selection) in expected.iter().enumerate() {
+        let indices = g.indices().to_vec();
+        assert_eq!(rank_grouped(&sizes, &indices), Some(r));
+        assert_eq!(unrank_grouped(&sizes, r), Some(indices));
+        assert_eq!(GroupedCombinations::from_rank(&groups, r).collect::<Vec<_>>(), expected[r..]);
+        assert_eq!(g.len(), expected.len() - r);
+        assert_eq!(g.next_slice(), Some(&selection[..]));
+    }
+    assert_eq!(g.next_slice(), None);
+    assert_eq!(GroupedCombinations::new(&groups).nth(20), Some(expected[20].clone()));
+    assert_eq!(GroupedCombinations::new(&groups).nth(36), None);
+}
+
+#[test]
+fn grouped_edge_cases() {
+    let a = [1, 2, 3];
+    assert_eq!(GroupedCombinations::new(&[(&a[..], 0)]).next(), None);
+    assert_eq!(GroupedCombinations::new(&[(&a[..], 4)]).next(), None);
+    assert_eq!(GroupedCombinations::<i32>::new(&[]).next(), None);
+    assert_eq!(count_grouped(&[]), Some(0));
+    assert_eq!(rank_grouped(&[(3, 2)], &[1, 3]), None);
+    assert_eq!(rank_grouped(&[(3, 2)], &[1]), None);
+    assert_eq!(unrank_grouped(&[(3, 0)], 0), None);
+
+    // the first group alone has more choices than a u128 holds, but the first few selections can still be ranked
+    let sizes = [(300, 150), (2, 1)];
+    assert_eq!(count_grouped(&sizes), None);
+    assert_eq!(rank_grouped(&sizes, &unrank_grouped(&sizes, 5).unwrap()), Some(5));
+
+    // a later group too large to count takes the whole rank as its digit
+    let sizes = [(2, 1), (300, 150)];
+    let first: Vec<usize> = std::iter::once(0).chain(0..150).collect();
+    assert_eq!(unrank_grouped(&sizes, 0), Some(first));
+    assert_eq!(rank_grouped(&sizes, &unrank_grouped(&sizes, 7).unwrap()), Some(7));
+    let big: Vec<usize> = (0..300).collect();
+    let groups = [(&a[..2], 1), (&big[..], 150)];
+    let mut g = GroupedCombinations::new(&groups);
+    let second = g.nth(1).unwrap();
+    assert_eq!((*second[0], *second[149], *second[150]), (1, 148, 150));
+}
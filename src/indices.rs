@@ -0,0 +1,243 @@
+use crate::cursor::Cursor;
+use crate::{unrank, Order};
+use std::fmt;
+use std::iter::FusedIterator;
+
+/// Iterates over all possible combinations of the indices `0..n`, without a slice of items behind them.
+/// 
+/// The combinations are in the same order as those of [`CombinationIterator`], and only the current indices are ever
+/// stored, so `n` can be far larger than anything that could be held in memory.
+/// 
+/// # Examples
+/// 
+/// ```
+/// use gen_combinations::IndexCombinations;
+/// 
+/// let mut c = IndexCombinations::new(1 << 40, 2);
+/// assert_eq!(c.next_slice(), Some(&[0, 1][..]));
+/// assert_eq!(c.next_back(), Some(vec![(1 << 40) - 2, (1 << 40) - 1]));
+/// ```
+/// 
+/// [`CombinationIterator`]: struct.CombinationIterator.html
+#[derive(Clone, Debug)]
+pub struct IndexCombinations {
+    cursor: Cursor,
+    buffer: Vec<usize>, // reused by next_slice
+}
+
+impl IndexCombinations {
+    /// Creates an iterator over combinations of the indices `0..n` with length `k`.
+    /// 
+    /// If `k` is 0 or greater than `n`, the iterator will produce no values.
+    pub fn new(n: usize, k: usize) -> IndexCombinations {
+        IndexCombinations { cursor: Cursor::new(n, k), buffer: Vec::new() }
+    }
+
+    /// Creates an iterator over combinations of the indices `0..n` with length `k`, starting at the combination with
+    /// the given lexicographic `rank` (see [`rank`]).
+    /// 
+    /// If `rank` is past the last combination, the iterator will produce no values.
+    /// 
+    /// [`rank`]: fn.rank.html
+    pub fn from_rank(n: usize, k: usize, rank: usize) -> IndexCombinations {
+        let front = if k == 0 { Vec::new() } else { unrank(n, k, rank).unwrap_or_default() };
+        IndexCombinations { cursor: Cursor::starting_at(n, front, Order::Lexicographic), buffer: Vec::new() }
+    }
+
+    /// Returns the number of combinations left, or `None` if it does not fit in a `usize` and [`len`] would panic.
+    /// 
+    /// [`len`]: https://doc.rust-lang.org/std/iter/trait.ExactSizeIterator.html#method.len
+    pub fn remaining(&self) -> Option<usize> {
+        self.cursor.remaining()
+    }
+
+    /// Returns the indices of the next combination from the front, or an empty slice if there are no combinations
+    /// left.
+    pub fn indices(&self) -> &[usize] {
+        self.cursor.front()
+    }
+
+    /// Advances the iterator and returns the next combination in a buffer owned by the iterator instead of a newly
+    /// allocated `Vec`.
+    pub fn next_slice(&mut self) -> Option<&[usize]> {
+        let buffer = &mut self.buffer;
+        self.cursor.next_front(|combo| {
+            buffer.clear();
+            buffer.extend_from_slice(combo);
+        })?;
+        Some(&self.buffer)
+    }
+}
+
+impl Iterator for IndexCombinations {
+    type Item = Vec<usize>;
+
+    fn next(&mut self) -> Option<Vec<usize>> {
+        self.cursor.next_front(<[usize]>::to_vec)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.cursor.size_hint()
+    }
+
+    fn count(self) -> usize {
+        self.cursor.len()
+    }
+
+    fn nth(&mut self, n: usize) -> Option<Vec<usize>> {
+        self.cursor.nth_front(n, <[usize]>::to_vec)
+    }
+}
+
+impl DoubleEndedIterator for IndexCombinations {
+    fn next_back(&mut self) -> Option<Vec<usize>> {
+        self.cursor.next_back(<[usize]>::to_vec)
+    }
+
+    fn nth_back(&mut self, n: usize) -> Option<Vec<usize>> {
+        self.cursor.nth_back(n, <[usize]>::to_vec)
+    }
+}
+
+impl ExactSizeIterator for IndexCombinations {}
+
+impl FusedIterator for IndexCombinations {}
+
+/// Iterates over all possible combinations of the indices `0..n`, passing each index through a function to produce
+/// the items.
+/// 
+/// This is useful when the items can be computed or fetched on demand from their index. The function is called once
+/// for each item of each combination produced.
+/// 
+/// # Examples
+/// 
+/// ```
+/// use gen_combinations::MappedCombinations;
+/// 
+/// let squares: Vec<_> = MappedCombinations::new(4, 3, |i| i * i).collect();
+/// assert_eq!(squares, [[0, 1, 4], [0, 1, 9], [0, 4, 9], [1, 4, 9]]);
+/// ```
+#[derive(Clone)]
+pub struct MappedCombinations<F> {
+    cursor: Cursor,
+    f: F,
+}
+
+impl<T, F: Fn(usize) -> T> MappedCombinations<F> {
+    /// Creates an iterator over combinations of the indices `0..n` with length `k`, mapped through `f`.
+    /// 
+    /// If `k` is 0 or greater than `n`, the iterator will produce no values.
+    pub fn new(n: usize, k: usize, f: F) -> MappedCombinations<F> {
+        MappedCombinations { cursor: Cursor::new(n, k), f }
+    }
+
+    /// Returns the number of combinations left, or `None` if it does not fit in a `usize` and [`len`] would panic.
+    /// 
+    /// [`len`]: https://doc.rust-lang.org/std/iter/trait.ExactSizeIterator.html#method.len
+    pub fn remaining(&self) -> Option<usize> {
+        self.cursor.remaining()
+    }
+
+    /// Returns the indices of the next combination from the front, or an empty slice if there are no combinations
+    /// left.
+    pub fn indices(&self) -> &[usize] {
+        self.cursor.front()
+    }
+
+    /// Advances the iterator and writes the next combination into `combo`, replacing its contents.
+    /// 
+    /// Returns `false`, leaving `combo` unchanged, if there are no combinations left.
+    pub fn next_into(&mut self, combo: &mut Vec<T>) -> bool {
+        let f = &self.f;
+        self.cursor
+            .next_front(|indices| {
+                combo.clear();
+                combo.extend(indices.iter().map(|&i| f(i)));
+            })
+            .is_some()
+    }
+}
+
+impl<F> fmt::Debug for MappedCombinations<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MappedCombinations").field("cursor", &self.cursor).finish()
+    }
+}
+
+impl<T, F: Fn(usize) -> T> Iterator for MappedCombinations<F> {
+    type Item = Vec<T>;
+
+    fn next(&mut self) -> Option<Vec<T>> {
+        let f = &self.f;
+        self.cursor.next_front(|combo| combo.iter().map(|&i| f(i)).collect())
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.cursor.size_hint()
+    }
+
+    fn count(self) -> usize {
+        self.cursor.len()
+    }
+
+    fn nth(&mut self, n: usize) -> Option<Vec<T>> {
+        let f = &self.f;
+        self.cursor.nth_front(n, |combo| combo.iter().map(|&i| f(i)).collect())
+    }
+}
+
+impl<T, F: Fn(usize) -> T> DoubleEndedIterator for MappedCombinations<F> {
+    fn next_back(&mut self) -> Option<Vec<T>> {
+        let f = &self.f;
+        self.cursor.next_back(|combo| combo.iter().map(|&i| f(i)).collect())
+    }
+
+    fn nth_back(&mut self, n: usize) -> Option<Vec<T>> {
+        let f = &self.f;
+        self.cursor.nth_back(n, |combo| combo.iter().map(|&i| f(i)).collect())
+    }
+}
+
+impl<T, F: Fn(usize) -> T> ExactSizeIterator for MappedCombinations<F> {}
+
+impl<T, F: Fn(usize) -> T> FusedIterator for MappedCombinations<F> {}
+
+#[test]
+fn index_combinations_match_items() {
+    let items: Vec<usize> = (0..6).collect();
+    let expected: Vec<Vec<usize>> =
+        crate::CombinationIterator::new(&items, 3).map(|c| c.into_iter().copied().collect()).collect();
+    assert_eq!(IndexCombinations::new(6, 3).collect::<Vec<_>>(), expected);
+    assert_eq!(IndexCombinations::new(6, 3).len(), 20);
+    assert_eq!(IndexCombinations::from_rank(6, 3, 7).next(), Some(expected[7].clone()));
+
+    let mut c = IndexCombinations::new(6, 3);
+    let mut lent = Vec::new();
+    while let Some(combo) = c.next_slice() {
+        lent.push(combo.to_vec());
+    }
+    assert_eq!(lent, expected);
+}
+
+#[test]
+fn index_combinations_huge() {
+    let n = usize::MAX / 2;
+    let mut c = IndexCombinations::new(n, 3);
+    assert_eq!(c.size_hint(), (usize::MAX, None));
+    assert_eq!(c.nth(n - 3), Some(vec![0, 1, n - 1]));
+    assert_eq!(c.next(), Some(vec![0, 2, 3]));
+    assert_eq!(c.indices(), &[0, 2, 4]);
+    assert_eq!(c.next_back(), Some(vec![n - 3, n - 2, n - 1]));
+}
+
+#[test]
+fn mapped_combinations() {
+    let mut c = MappedCombinations::new(4, 2, |i| i.to_string());
+    assert_eq!(c.len(), 6);
+    let mut combo = Vec::new();
+    assert!(c.next_into(&mut combo));
+    assert_eq!(combo, ["0", "1"]);
+    assert_eq!(c.indices(), &[0, 2]);
+    assert_eq!(c.next_back(), Some(vec![String::from("2"), String::from("3")]));
+    assert_eq!(c.count(), 4);
+}
@@ -0,0 +1,1003 @@
+use crate::remaining::{Ranked, Remaining};
+use crate::subsets::bounds;
+use crate::narrow;
+use crate::natural::Natural;
+use std::iter::{self, FusedIterator};
+use std::ops::{Bound, RangeBounds};
+
+/// Iterates over the compositions of an integer, the ways to write it as an ordered sum of parts.
+/// 
+/// Each composition is a `Vec` of its parts. The number of parts and their sizes may be limited to ranges.
+/// Compositions are produced in lexicographic order, so one that begins another comes first. There are
+/// [`count_compositions`]`(n, parts, sizes)` of them.
+/// 
+/// With no largest part, a composition of `n` into `k` positive parts is just a choice of `k - 1` of the `n - 1` gaps
+/// between `n` units at which to cut them, and these are in the same order, with the same ranks (see [`rank`]), as
+/// the combinations of gaps.
+/// 
+/// # Examples
+/// 
+/// ```
+/// use gen_combinations::Compositions;
+/// 
+/// // every way to split a budget of 4 between 3 teams, giving each at most 2
+/// for split in Compositions::with_sizes(4, 3, ..=2) {
+///     println!("{:?}", split);
+///     // [0, 2, 2]
+///     // [1, 1, 2]
+///     // [1, 2, 1]
+///     // [2, 0, 2]
+///     // [2, 1, 1]
+///     // [2, 2, 0]
+/// }
+/// assert_eq!(Compositions::with_bounds(4, 2..=3, ..=2).count(), 7);
+/// ```
+/// 
+/// [`count_compositions`]: fn.count_compositions.html
+/// [`rank`]: fn.rank.html
+#[derive(Clone, Debug)]
+pub struct Compositions {
+    limits: CompositionLimits,
+    front: Vec<usize>, // the next composition
+    remaining: Remaining,
+    buffer: Vec<usize>, // reused by next_slice
+}
+
+impl Compositions {
+    /// Creates an iterator over the compositions of `n` into `k` positive parts.
+    /// 
+    /// If `k` is 0 or greater than `n`, the iterator will produce no values.
+    pub fn new(n: usize, k: usize) -> Compositions {
+        Compositions::with_bounds(n, k..=k, 1..)
+    }
+
+    /// Creates an iterator over the weak compositions of `n` into `k` parts, where parts may be 0.
+    /// 
+    /// These are the ways to put `n` identical items into `k` distinct boxes, often drawn as stars and bars. If `k` is
+    /// 0, the iterator will produce no values.
+    pub fn weak(n: usize, k: usize) -> Compositions {
+        Compositions::with_bounds(n, k..=k, ..)
+    }
+
+    /// Creates an iterator over the compositions of `n` into `k` parts with sizes in the range `sizes`.
+    /// 
+    /// If `k` is 0 or there are no such compositions, the iterator will produce no values.
+    pub fn with_sizes<R: RangeBounds<usize>>(n: usize, k: usize, sizes: R) -> Compositions {
+        Compositions::with_bounds(n, k..=k, sizes)
+    }
+
+    /// Creates an iterator over the compositions of `n` with a number of parts in the range `parts` and sizes in the
+    /// range `sizes`.
+    /// 
+    /// Compositions always have at least one part. If there are no such compositions, or infinitely many because
+    /// parts may be 0 and there is no most number of them, the iterator will produce no values.
+    pub fn with_bounds<P, S>(n: usize, parts: P, sizes: S) -> Compositions
+    where
+        P: RangeBounds<usize>,
+        S: RangeBounds<usize>,
+    {
+        let limits = CompositionLimits::new(n, &parts, &sizes).filter(|limits| limits.largest_rest(n, 0) == Some(n));
+        let mut front = Vec::new();
+        let remaining = match limits {
+            Some(limits) => {
+                limits.fill(&mut front, n);
+                Remaining::new(limits.count(n, n, 0))
+            }
+            None => Remaining::zero(),
+        };
+        let limits = limits.unwrap_or(CompositionLimits { lo: 1, hi: 0, fewest: 1, most: 0 });
+        Compositions { limits, front, remaining, buffer: Vec::new() }
+    }
+
+    /// Returns the number of compositions left, or `None` if it does not fit in a `usize` and [`len`] would panic.
+    /// 
+    /// [`len`]: https://doc.rust-lang.org/std/iter/trait.ExactSizeIterator.html#method.len
+    pub fn remaining(&self) -> Option<usize> {
+        self.remaining.get()
+    }
+
+    /// Returns the next composition, or an empty slice if there are none left.
+    pub fn parts(&self) -> &[usize] {
+        &self.front
+    }
+
+    /// Advances the iterator and returns the next composition in a buffer owned by the iterator instead of a newly
+    /// allocated `Vec`.
+    pub fn next_slice(&mut self) -> Option<&[usize]> {
+        if self.remaining.is_zero() {
+            return None;
+        }
+        self.buffer.clear();
+        self.buffer.extend_from_slice(&self.front);
+        self.step();
+        Some(&self.buffer)
+    }
+
+    fn count_remaining(&self) -> Option<u128> {
+        let n = self.front.iter().sum();
+        Some(self.limits.count(n, n, 0)? - self.limits.rank(&self.front)?)
+    }
+}
+
+impl Ranked for Compositions {
+    fn left(&self) -> Remaining {
+        self.remaining
+    }
+
+    fn rank(&self) -> Option<u128> {
+        self.limits.rank(&self.front)
+    }
+
+    fn jump(&mut self, rank: u128, by: usize) {
+        let sum = self.front.iter().sum();
+        self.limits.unrank_into(&mut self.front, sum, rank);
+        self.remaining = self.remaining.consumed(by, || self.count_remaining());
+    }
+
+    fn step(&mut self) {
+        if self.remaining.is_one() {
+            self.exhaust();
+            return;
+        }
+        self.limits.successor(&mut self.front);
+        self.remaining = self.remaining.consumed(1, || self.count_remaining());
+    }
+
+    fn exhaust(&mut self) {
+        self.front.clear();
+        self.remaining = Remaining::zero();
+    }
+}
+
+impl Iterator for Compositions {
+    type Item = Vec<usize>;
+
+    fn next(&mut self) -> Option<Vec<usize>> {
+        self.next_slice().map(<[usize]>::to_vec)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.remaining.size_hint()
+    }
+
+    fn count(self) -> usize {
+        self.remaining.get().expect("more than usize::MAX compositions")
+    }
+
+    fn nth(&mut self, n: usize) -> Option<Vec<usize>> {
+        if self.skip_ahead(n) {
+            self.next()
+        } else {
+            None
+        }
+    }
+}
+
+impl ExactSizeIterator for Compositions {}
+
+impl FusedIterator for Compositions {}
+
+/// Returns the number of compositions of `n` with a number of parts in the range `parts` and sizes in the range
+/// `sizes`, or `None` if it does not fit in a `usize`.
+/// 
+/// # Examples
+/// 
+/// ```
+/// assert_eq!(gen_combinations::count_compositions(4, 3..=3, 1..), Some(3));
+/// assert_eq!(gen_combinations::count_compositions(4, 3..=3, ..), Some(15));
+/// assert_eq!(gen_combinations::count_compositions(4, 3..=3, ..=2), Some(6));
+/// assert_eq!(gen_combinations::count_compositions(4, .., 1..), Some(8));
+/// ```
+pub fn count_compositions<P, S>(n: usize, parts: P, sizes: S) -> Option<usize>
+where
+    P: RangeBounds<usize>,
+    S: RangeBounds<usize>,
+{
+    Compositions::with_bounds(n, parts, sizes).remaining.get()
+}
+
+/// Returns the lexicographic rank of `composition` among the compositions of its sum with a number of parts in the
+/// range `parts` and sizes in the range `sizes`.
+/// 
+/// Returns `None` if `composition` is empty, if it does not keep to `parts` and `sizes`, or if the rank does not fit
+/// in a `usize`.
+/// 
+/// # Examples
+/// 
+/// ```
+/// assert_eq!(gen_combinations::rank_composition(3..=3, 1.., &[1, 1, 2]), Some(0));
+/// assert_eq!(gen_combinations::rank_composition(3..=3, ..=2, &[2, 1, 1]), Some(4));
+/// assert_eq!(gen_combinations::rank_composition(3..=3, ..=2, &[3, 1, 0]), None);
+/// assert_eq!(gen_combinations::rank_composition(.., 1.., &[1, 3]), Some(3));
+/// ```
+pub fn rank_composition<P, S>(parts: P, sizes: S, composition: &[usize]) -> Option<usize>
+where
+    P: RangeBounds<usize>,
+    S: RangeBounds<usize>,
+{
+    let n = composition.iter().try_fold(0usize, |sum, &part| sum.checked_add(part))?;
+    let limits = CompositionLimits::new(n, &parts, &sizes)?;
+    if composition.len() < limits.fewest
+        || composition.len() > limits.most
+        || composition.iter().any(|&part| part < limits.lo || part > limits.hi)
+    {
+        return None;
+    }
+    narrow(limits.rank(composition)?)
+}
+
+/// Returns the composition of `n` with a number of parts in the range `parts` and sizes in the range `sizes` that
+/// has the given lexicographic `rank`.
+/// 
+/// This is the inverse of [`rank_composition`]. Returns `None` if there are not more than `rank` such compositions.
+/// 
+/// # Examples
+/// 
+/// ```
+/// assert_eq!(gen_combinations::unrank_composition(4, 3..=3, ..=2, 4), Some(vec![2, 1, 1]));
+/// assert_eq!(gen_combinations::unrank_composition(4, 3..=3, ..=2, 6), None);
+/// ```
+/// 
+/// [`rank_composition`]: fn.rank_composition.html
+pub fn unrank_composition<P, S>(n: usize, parts: P, sizes: S, rank: usize) -> Option<Vec<usize>>
+where
+    P: RangeBounds<usize>,
+    S: RangeBounds<usize>,
+{
+    let mut c = Compositions::with_bounds(n, parts, sizes);
+    if !c.remaining.exceeds(rank) {
+        return None;
+    }
+    c.limits.unrank_into(&mut c.front, n, rank as u128);
+    Some(c.front)
+}
+
+/// The limits on the number of parts and their sizes in the compositions of an integer.
+#[derive(Clone, Copy, Debug)]
+struct CompositionLimits {
+    lo: usize,
+    hi: usize,
+    fewest: usize,
+    most: usize,
+}
+
+impl CompositionLimits {
+    /// Returns the limits for compositions of `n`, or `None` if they rule out every part or number of parts, or allow
+    /// any number of parts of 0.
+    fn new<P, S>(n: usize, parts: &P, sizes: &S) -> Option<CompositionLimits>
+    where
+        P: RangeBounds<usize>,
+        S: RangeBounds<usize>,
+    {
+        let (lo, hi) = bounds(n, sizes)?;
+        let cap = match (lo, parts.end_bound()) {
+            (0, Bound::Unbounded) => return None,
+            (0, _) => usize::MAX,
+            _ => n / lo,
+        };
+        let (fewest, most) = bounds(cap, parts)?;
+        let fewest = fewest.max(1);
+        if fewest > most {
+            None
+        } else {
+            Some(CompositionLimits { lo, hi, fewest, most })
+        }
+    }
+
+    /// Returns the largest sum up to `at_most` that the parts after the first `done` can add up to, or `None` if there
+    /// is none.
+    fn largest_rest(&self, at_most: usize, done: usize) -> Option<usize> {
+        let fewest = self.fewest.saturating_sub(done);
+        let most = self.most.checked_sub(done)?;
+        // r parts add up to anything from r * lo to r * hi, so the largest number of parts that fits reaches highest
+        let r = at_most.checked_div(self.lo).map_or(most, |r| most.min(r));
+        if r < fewest {
+            None
+        } else {
+            Some(at_most.min(r.saturating_mul(self.hi)))
+        }
+    }
+
+    /// Appends to `parts` the first parts adding up to `sum` after them, which must exist.
+    fn fill(&self, parts: &mut Vec<usize>, sum: usize) {
+        let mut rest = sum;
+        while rest > 0 || parts.len() < self.fewest {
+            // each part is as small as it can be while the parts after it can still take up the rest
+            let after = self.largest_rest(rest - self.lo, parts.len() + 1).expect("the parts can add up to the sum");
+            parts.push(rest - after);
+            rest = after;
+        }
+    }
+
+    /// Moves the composition in `parts` to the next one in lexicographic order.
+    /// 
+    /// Returns `false`, leaving `parts` unchanged, if it is already the last one.
+    fn successor(&self, parts: &mut Vec<usize>) -> bool {
+        // one more part of 0 comes right after
+        if self.lo == 0 && parts.len() < self.most {
+            parts.push(0);
+            return true;
+        }
+        let mut rest = 0;
+        for i in (0..parts.len()).rev() {
+            rest += parts[i];
+            // the next bigger part i leaves the most the parts after it can add up to
+            let after = (rest - parts[i]).checked_sub(1).and_then(|at_most| self.largest_rest(at_most, i + 1));
+            if let Some(after) = after.filter(|&after| rest - after <= self.hi) {
+                parts[i] = rest - after;
+                parts.truncate(i + 1);
+                self.fill(parts, after);
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Returns the number of ways for the parts after the first `done` to add up to from `from` to `to`, or `None` if
+    /// it does not fit in a `u128`.
+    fn count(&self, from: usize, to: usize, done: usize) -> Option<u128> {
+        let fewest = self.fewest.saturating_sub(done);
+        let most = self.most - done;
+        let tally = Tally { h: self.hi - self.lo };
+        let mut count = (from == 0 && fewest == 0) as u128;
+        // r parts add up to at least r * lo and at most r * hi, so only some numbers of parts reach the range
+        let mut first = fewest.max(1);
+        if self.hi > 0 {
+            first = first.max(from.div_ceil(self.hi));
+        }
+        let last = to.checked_div(self.lo).map_or(most, |r| most.min(r));
+        for r in first..=last {
+            let least = r * self.lo;
+            count = count.checked_add(tally.between(r, from.max(least) - least, to - least)?)?;
+        }
+        Some(count)
+    }
+
+    /// Returns the rank of a composition, or `None` if it does not fit in a `u128`.
+    fn rank(&self, parts: &[usize]) -> Option<u128> {
+        let mut rest: usize = parts.iter().sum();
+        let mut rank = 0u128;
+        for (i, &part) in parts.iter().enumerate() {
+            // the composition that stops before i comes first, as does every one that agrees before i but has a
+            // smaller part there
+            if rest == 0 && i >= self.fewest {
+                rank = rank.checked_add(1)?;
+            }
+            if part > self.lo {
+                rank = rank.checked_add(self.count(rest - part + 1, rest - self.lo, i + 1)?)?;
+            }
+            rest -= part;
+        }
+        Some(rank)
+    }
+
+    /// Fills `parts` with the composition of `n` with the given rank, which must be less than the number of them.
+    fn unrank_into(&self, parts: &mut Vec<usize>, n: usize, rank: u128) {
+        let mut rank = rank;
+        let mut rest = n;
+        parts.clear();
+        loop {
+            let i = parts.len();
+            if rest == 0 && i >= self.fewest {
+                if rank == 0 {
+                    return;
+                }
+                rank -= 1;
+            }
+            let r = self.most - i - 1;
+            let (mut a, mut b) = (self.lo.max(rest.saturating_sub(r.saturating_mul(self.hi))), self.hi.min(rest));
+            // find the largest part such that at most `rank` compositions have a smaller one, and if there are too many
+            // of those to count, they are more than any rank
+            let before = |x: usize| if x > self.lo { self.count(rest - x + 1, rest - self.lo, i + 1) } else { Some(0) };
+            let mut skipped = 0;
+            while a < b {
+                let mid = b - (b - a) / 2;
+                match before(mid) {
+                    Some(count) if count <= rank => {
+                        a = mid;
+                        skipped = count;
+                    }
+                    _ => b = mid - 1,
+                }
+            }
+            rank -= skipped;
+            parts.push(a);
+            rest -= a;
+        }
+    }
+}
+
+/// The number of ways for parts from 0 to `h` to add up to numbers in a range, used to count compositions once the
+/// smallest size has been taken off each part.
+#[derive(Clone, Copy, Debug)]
+struct Tally {
+    h: usize,
+}
+
+impl Tally {
+    /// Returns the number of ways for `r` parts to add up to from `from` to `to`, or `None` if it does not fit in a
+    /// `u128`.
+    fn between(&self, r: usize, from: usize, to: usize) -> Option<u128> {
+        let (mut from, mut to) = (from, to);
+        if let Some(top) = r.checked_mul(self.h) {
+            to = to.min(top);
+            // the parts add up to s just when h less each of them add up to top - s, and smaller sums have smaller
+            // terms in the sum below
+            if from <= to && from > top - to {
+                (from, to) = (top - to, top - from);
+            }
+        }
+        if from > to {
+            return Some(0);
+        }
+        // the ways to add up to at most x are combinations of r bars among x units and r bars, one more part taking
+        // up the slack, and inclusion–exclusion takes away those with a part over h by giving j of them h + 1 first;
+        // the terms may be far bigger than the sum, so those added and those taken away are summed apart in full
+        let (mut added, mut taken) = (Natural::new(0), Natural::new(0));
+        let below = from.checked_sub(1).map(|x| (x, false));
+        for (x, plus) in iter::once((to, true)).chain(below) {
+            let mut ways = Natural::new(1);
+            for j in 0..=r {
+                let given = match self.h.checked_add(1).and_then(|over| over.checked_mul(j)) {
+                    Some(given) if given <= x => given,
+                    _ => break,
+                };
+                let term = ways.times(&Natural::binomial((x - given) as u128 + r as u128, r as u128));
+                if plus == (j % 2 == 0) {
+                    added.add(&term);
+                } else {
+                    taken.add(&term);
+                }
+                // C(r, j + 1) = C(r, j) * (r - j) / (j + 1)
+                ways = ways.times(&Natural::new((r - j) as u128));
+                ways.divide(j as u64 + 1);
+            }
+        }
+        added.subtract(&taken);
+        added.narrow()
+    }
+}
+
+/// Iterates over the partitions of an integer, the ways to write it as a sum of parts where their order does not
+/// matter.
+/// 
+/// Each partition is a `Vec` of its parts from largest to smallest. The number of parts and their sizes may be
+/// limited to ranges. Partitions are produced in lexicographic order, so the first has the most and smallest parts.
+/// There are [`count_integer_partitions`]`(n, parts, sizes)` of them.
+/// 
+/// Parts are always at least 1, and if `n` is 0, the iterator will produce no values.
+/// 
+/// # Examples
+/// 
+/// ```
+/// use gen_combinations::IntegerPartitions;
+/// 
+/// for partition in IntegerPartitions::new(5) {
+///     println!("{:?}", partition);
+///     // [1, 1, 1, 1, 1]
+///     // [2, 1, 1, 1]
+///     // [2, 2, 1]
+///     // [3, 1, 1]
+///     // [3, 2]
+///     // [4, 1]
+///     // [5]
+/// }
+/// assert_eq!(IntegerPartitions::with_bounds(5, 2..=3, ..=3).count(), 3);
+/// ```
+/// 
+/// [`count_integer_partitions`]: fn.count_integer_partitions.html
+#[derive(Clone, Debug)]
+pub struct IntegerPartitions {
+    limits: Limits,
+    tally: PartitionTally,
+    front: Vec<usize>, // the next partition
+    remaining: Remaining,
+    buffer: Vec<usize>, // reused by next_slice
+}
+
+impl IntegerPartitions {
+    /// Creates an iterator over all partitions of `n`.
+    pub fn new(n: usize) -> IntegerPartitions {
+        IntegerPartitions::with_bounds(n, .., ..)
+    }
+
+    /// Creates an iterator over the partitions of `n` with a number of parts in the range `parts` and sizes in the
+    /// range `sizes`.
+    /// 
+    /// If there are no such partitions, the iterator will produce no values.
+    pub fn with_bounds<P, S>(n: usize, parts: P, sizes: S) -> IntegerPartitions
+    where
+        P: RangeBounds<usize>,
+        S: RangeBounds<usize>,
+    {
+        let limits = Limits::new(n, &parts, &sizes);
+        let first = limits.and_then(|limits| limits.fill_count(n, limits.hi, 0));
+        let limits = limits.filter(|_| first.is_some()).unwrap_or(Limits { lo: 1, hi: 0, fewest: 1, most: 0 });
+        let tally = PartitionTally::new(n, limits);
+        let remaining = match first {
+            Some(_) => Remaining::new(tally.count(n, limits.hi, limits.fewest, limits.most)),
+            None => Remaining::zero(),
+        };
+        let mut front = Vec::new();
+        if let Some(count) = first {
+            fill_partition(&mut front, n, count);
+        }
+        IntegerPartitions { limits, tally, front, remaining, buffer: Vec::new() }
+    }
+
+    /// Returns the number of partitions left, or `None` if it does not fit in a `usize` and [`len`] would panic.
+    /// 
+    /// [`len`]: https://doc.rust-lang.org/std/iter/trait.ExactSizeIterator.html#method.len
+    pub fn remaining(&self) -> Option<usize> {
+        self.remaining.get()
+    }
+
+    /// Returns the next partition, or an empty slice if there are none left.
+    pub fn parts(&self) -> &[usize] {
+        &self.front
+    }
+
+    /// Advances the iterator and returns the next partition in a buffer owned by the iterator instead of a newly
+    /// allocated `Vec`.
+    pub fn next_slice(&mut self) -> Option<&[usize]> {
+        if self.remaining.is_zero() {
+            return None;
+        }
+        self.buffer.clear();
+        self.buffer.extend_from_slice(&self.front);
+        self.step();
+        Some(&self.buffer)
+    }
+
+    fn count_remaining(&self) -> Option<u128> {
+        let n = self.front.iter().sum();
+        let count = self.tally.count(n, self.limits.hi, self.limits.fewest, self.limits.most)?;
+        Some(count - wide_integer_partition_rank(&self.tally, self.limits, &self.front)?)
+    }
+}
+
+impl Ranked for IntegerPartitions {
+    fn left(&self) -> Remaining {
+        self.remaining
+    }
+
+    fn rank(&self) -> Option<u128> {
+        wide_integer_partition_rank(&self.tally, self.limits, &self.front)
+    }
+
+    fn jump(&mut self, rank: u128, by: usize) {
+        let sum = self.front.iter().sum();
+        integer_partition_unrank_into(&self.tally, self.limits, &mut self.front, sum, rank);
+        self.remaining = self.remaining.consumed(by, || self.count_remaining());
+    }
+
+    fn step(&mut self) {
+        if self.remaining.is_one() {
+            self.exhaust();
+            return;
+        }
+        self.limits.successor(&mut self.front);
+        self.remaining = self.remaining.consumed(1, || self.count_remaining());
+    }
+
+    fn exhaust(&mut self) {
+        self.front.clear();
+        self.remaining = Remaining::zero();
+    }
+}
+
+impl Iterator for IntegerPartitions {
+    type Item = Vec<usize>;
+
+    fn next(&mut self) -> Option<Vec<usize>> {
+        self.next_slice().map(<[usize]>::to_vec)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.remaining.size_hint()
+    }
+
+    fn count(self) -> usize {
+        self.remaining.get().expect("more than usize::MAX partitions")
+    }
+
+    fn nth(&mut self, n: usize) -> Option<Vec<usize>> {
+        if self.skip_ahead(n) {
+            self.next()
+        } else {
+            None
+        }
+    }
+}
+
+impl ExactSizeIterator for IntegerPartitions {}
+
+impl FusedIterator for IntegerPartitions {}
+
+/// Returns the number of partitions of `n` with a number of parts in the range `parts` and sizes in the range `sizes`,
+/// or `None` if it does not fit in a `usize`.
+/// 
+/// # Examples
+/// 
+/// ```
+/// assert_eq!(gen_combinations::count_integer_partitions(5, .., ..), Some(7));
+/// assert_eq!(gen_combinations::count_integer_partitions(5, 2..=3, ..=3), Some(3));
+/// assert_eq!(gen_combinations::count_integer_partitions(100, .., ..), Some(190569292));
+/// ```
+pub fn count_integer_partitions<P, S>(n: usize, parts: P, sizes: S) -> Option<usize>
+where
+    P: RangeBounds<usize>,
+    S: RangeBounds<usize>,
+{
+    IntegerPartitions::with_bounds(n, parts, sizes).remaining.get()
+}
+
+/// Returns the lexicographic rank of `partition`, given from its largest part to its smallest, among the partitions
+/// of its sum with a number of parts in the range `parts` and sizes in the range `sizes`.
+/// 
+/// Returns `None` if `partition` is empty or not in that order, if it does not keep to `parts` and `sizes`, or if the
+/// rank does not fit in a `usize`.
+/// 
+/// # Examples
+/// 
+/// ```
+/// assert_eq!(gen_combinations::rank_integer_partition(.., .., &[2, 2, 1]), Some(2));
+/// assert_eq!(gen_combinations::rank_integer_partition(.., .., &[2, 1, 2]), None);
+/// ```
+pub fn rank_integer_partition<P, S>(parts: P, sizes: S, partition: &[usize]) -> Option<usize>
+where
+    P: RangeBounds<usize>,
+    S: RangeBounds<usize>,
+{
+    let n = partition.iter().try_fold(0usize, |sum, &part| sum.checked_add(part))?;
+    let limits = Limits::new(n, &parts, &sizes)?;
+    if partition.windows(2).any(|w| w[0] < w[1])
+        || partition.len() < limits.fewest
+        || partition.len() > limits.most
+        || partition.iter().any(|&part| part < limits.lo || part > limits.hi)
+    {
+        return None;
+    }
+    narrow(wide_integer_partition_rank(&PartitionTally::new(n, limits), limits, partition)?)
+}
+
+/// Returns the partition of `n`, from its largest part to its smallest, with a number of parts in the range `parts`
+/// and sizes in the range `sizes` that has the given lexicographic `rank`.
+/// 
+/// This is the inverse of [`rank_integer_partition`]. Returns `None` if there are not more than `rank` such
+/// partitions.
+/// 
+/// # Examples
+/// 
+/// ```
+/// assert_eq!(gen_combinations::unrank_integer_partition(5, .., .., 2), Some(vec![2, 2, 1]));
+/// assert_eq!(gen_combinations::unrank_integer_partition(5, .., .., 7), None);
+/// ```
+/// 
+/// [`rank_integer_partition`]: fn.rank_integer_partition.html
+pub fn unrank_integer_partition<P, S>(n: usize, parts: P, sizes: S, rank: usize) -> Option<Vec<usize>>
+where
+    P: RangeBounds<usize>,
+    S: RangeBounds<usize>,
+{
+    let mut p = IntegerPartitions::with_bounds(n, parts, sizes);
+    if !p.remaining.exceeds(rank) {
+        return None;
+    }
+    integer_partition_unrank_into(&p.tally, p.limits, &mut p.front, n, rank as u128);
+    Some(p.front)
+}
+
+/// The limits on the number of parts and their sizes in the partitions of an integer.
+#[derive(Clone, Copy, Debug)]
+struct Limits {
+    lo: usize,
+    hi: usize,
+    fewest: usize,
+    most: usize,
+}
+
+impl Limits {
+    /// Returns the limits for partitions of `n`, or `None` if they rule out every part or number of parts.
+    fn new<P, S>(n: usize, parts: &P, sizes: &S) -> Option<Limits>
+    where
+        P: RangeBounds<usize>,
+        S: RangeBounds<usize>,
+    {
+        let (lo, hi) = bounds(n, sizes)?;
+        let lo = lo.max(1);
+        let (fewest, most) = bounds(n / lo, parts)?;
+        if lo > hi {
+            None
+        } else {
+            Some(Limits { lo, hi, fewest, most })
+        }
+    }
+
+    /// Returns the number of parts in the first partition of `sum` into parts of at most `hi`, after `done` parts, or
+    /// `None` if there is no such partition.
+    fn fill_count(&self, sum: usize, hi: usize, done: usize) -> Option<usize> {
+        let fewest = self.fewest.saturating_sub(done);
+        let most = self.most - done;
+        if sum == 0 {
+            return if fewest == 0 { Some(0) } else { None };
+        }
+        // the first partition has as many parts as possible, as even as possible
+        let count = most.min(sum / self.lo);
+        if count == 0 || count < fewest || sum.div_ceil(count) > hi {
+            None
+        } else {
+            Some(count)
+        }
+    }
+
+    /// Moves the partition in `parts` to the next one in lexicographic order.
+    /// 
+    /// Returns `false`, leaving `parts` unchanged, if it is already the last one.
+    fn successor(&self, parts: &mut Vec<usize>) -> bool {
+        let mut after = 0;
+        for i in (0..parts.len().saturating_sub(1)).rev() {
+            after += parts[i + 1];
+            let total = parts[i] + after;
+            let top = if i > 0 { self.hi.min(parts[i - 1]) } else { self.hi }.min(total);
+            // take as little as the parts after i can spare for part i, as long as it stays in order
+            for part in parts[i] + 1..=top {
+                if let Some(count) = self.fill_count(total - part, part, i + 1) {
+                    parts[i] = part;
+                    parts.truncate(i + 1);
+                    fill_partition(parts, total - part, count);
+                    return true;
+                }
+            }
+        }
+        false
+    }
+}
+
+/// Appends `count` parts adding up to `sum`, as even as possible and from largest to smallest, to `parts`.
+fn fill_partition(parts: &mut Vec<usize>, sum: usize, count: usize) {
+    parts.extend((0..count).map(|j| sum / count + (j < sum % count) as usize));
+}
+
+/// How to count the partitions of numbers into parts from the smallest size up, with each number of parts if that is
+/// limited.
+/// 
+/// The counts are built up one size at a time in a [`Sweep`], so only those for the sizes so far are ever kept.
+#[derive(Clone, Copy, Debug)]
+struct PartitionTally {
+    lo: usize,
+    by_parts: bool,
+    rows: usize, // the numbers of parts counted apart, or 1 if they are not
+}
+
+/// The number of partitions of each number up to some total into parts from the smallest size to `v`.
+struct Sweep {
+    v: usize,
+    counts: Vec<Vec<Option<u128>>>, // [s][r] for a sum of s with r parts, or any number of parts if r is always 0
+}
+
+impl PartitionTally {
+    fn new(n: usize, limits: Limits) -> PartitionTally {
+        let Limits { lo, fewest, most, .. } = limits;
+        // every partition of n has from 1 to n / lo parts, so only other limits need counting by parts
+        let by_parts = fewest > 1 || most < n / lo;
+        let rows = if by_parts { most + 1 } else { 1 };
+        PartitionTally { lo, by_parts, rows }
+    }
+
+    /// Starts counting the partitions of each number up to `n` with no sizes of part yet.
+    fn sweep(&self, n: usize) -> Sweep {
+        let mut counts = vec![vec![Some(0); self.rows]; n + 1];
+        counts[0][0] = Some(1);
+        Sweep { v: self.lo - 1, counts }
+    }
+
+    /// Adds the next size of part to the sweep.
+    fn grow(&self, sweep: &mut Sweep) {
+        sweep.v += 1;
+        let v = sweep.v;
+        let counts = &mut sweep.counts;
+        // a partition either has no part of size v, or is one with a part of size v more
+        for s in v..counts.len() {
+            for r in 0..self.rows {
+                let more = match (self.by_parts, r) {
+                    (false, _) => counts[s - v][r],
+                    (true, 0) => Some(0),
+                    (true, _) => counts[s - v][r - 1],
+                };
+                counts[s][r] = counts[s][r].zip(more).and_then(|(count, more)| count.checked_add(more));
+            }
+        }
+    }
+
+    /// Returns the number of partitions of `s` in the sweep with from `fewest` to `most` parts, or `None` if it does
+    /// not fit in a `u128`.
+    fn total(&self, sweep: &Sweep, s: usize, fewest: usize, most: usize) -> Option<u128> {
+        let counts = &sweep.counts[s];
+        if self.by_parts {
+            counts.iter().take(most + 1).skip(fewest).try_fold(0u128, |sum, &count| sum.checked_add(count?))
+        } else {
+            counts[0]
+        }
+    }
+
+    /// Returns the number of partitions of `s` into parts from the smallest size to `v` with from `fewest` to `most`
+    /// parts, or `None` if it does not fit in a `u128`.
+    fn count(&self, s: usize, v: usize, fewest: usize, most: usize) -> Option<u128> {
+        let mut sweep = self.sweep(s);
+        while sweep.v < v {
+            self.grow(&mut sweep);
+        }
+        self.total(&sweep, s, fewest, most)
+    }
+}
+
+/// Returns the rank of a partition, or `None` if it does not fit in a `u128`.
+fn wide_integer_partition_rank(tally: &PartitionTally, limits: Limits, parts: &[usize]) -> Option<u128> {
+    // every partition that agrees before i but has a smaller part there comes first, and since the parts only get
+    // bigger towards the front, one sweep up through the sizes counts those for every i from the back
+    let mut rests: Vec<usize> = parts
+        .iter()
+        .rev()
+        .scan(0, |rest, &part| {
+            *rest += part;
+            Some(*rest)
+        })
+        .collect();
+    rests.reverse();
+    let mut sweep = tally.sweep(rests.first().copied().unwrap_or(0));
+    let mut rank = 0u128;
+    for (i, &part) in parts.iter().enumerate().rev() {
+        while sweep.v < part - 1 {
+            tally.grow(&mut sweep);
+        }
+        let before = tally.total(&sweep, rests[i], limits.fewest.saturating_sub(i), limits.most - i)?;
+        rank = rank.checked_add(before)?;
+    }
+    Some(rank)
+}
+
+/// Fills `parts` with the partition of `n` with the given rank, which must be less than the number of them.
+fn integer_partition_unrank_into(tally: &PartitionTally, limits: Limits, parts: &mut Vec<usize>, n: usize, rank: u128) {
+    let mut rank = rank;
+    let mut rest = n;
+    parts.clear();
+    while rest > 0 {
+        let (fewest, most) = (limits.fewest.saturating_sub(parts.len()), limits.most - parts.len());
+        // the first part such that more than `rank` partitions have a part at most that big here
+        let mut sweep = tally.sweep(rest);
+        let mut before = 0;
+        loop {
+            tally.grow(&mut sweep);
+            match tally.total(&sweep, rest, fewest, most) {
+                Some(count) if count <= rank => before = count,
+                _ => break,
+            }
+        }
+        rank -= before;
+        parts.push(sweep.v);
+        rest -= sweep.v;
+    }
+}
+
+#[test]
+fn compositions() {
+    use crate::{rank, unrank};
+
+    // brute force over every sequence of up to 4 parts up to 6
+    let every: Vec<Vec<usize>> = (1..=4u32)
+        .flat_map(|k| (0..7usize.pow(k)).map(move |code| (0..k).map(|i| code / 7usize.pow(k - 1 - i) % 7).collect()))
+        .collect();
+    for &(lo, hi) in &[(0, 6), (1, 6), (0, 2), (1, 3), (2, 2), (3, 6)] {
+        for &(fewest, most) in &[(4, 4), (1, 4), (2, 3), (1, 1)] {
+            for n in 0..=8 {
+                let mut expected: Vec<&Vec<usize>> = every
+                    .iter()
+                    .filter(|parts| fewest <= parts.len() && parts.len() <= most)
+                    .filter(|parts| parts.iter().sum::<usize>() == n && parts.iter().all(|&p| lo <= p && p <= hi))
+                    .collect();
+                expected.sort();
+                let c = Compositions::with_bounds(n, fewest..=most, lo..=hi);
+                assert_eq!(c.len(), expected.len());
+                assert_eq!(c.clone().collect::<Vec<_>>(), expected.iter().map(|p| p.to_vec()).collect::<Vec<_>>());
+                for (r, &parts) in expected.iter().enumerate() {
+                    assert_eq!(rank_composition(fewest..=most, lo..=hi, parts), Some(r));
+                    assert_eq!(unrank_composition(n, fewest..=most, lo..=hi, r).as_ref(), Some(parts));
+                    assert_eq!(c.clone().nth(r).as_ref(), Some(parts));
+                }
+            }
+        }
+    }
+    for n in 1..=12 {
+        assert_eq!(count_compositions(n, .., 1..), Some(1 << (n - 1)));
+    }
+    assert_eq!(Compositions::with_bounds(3, .., ..).next(), None);
+    assert_eq!(Compositions::with_bounds(0, ..=3, ..).collect::<Vec<_>>(), [vec![0], vec![0, 0], vec![0, 0, 0]]);
+
+    // positive compositions are cuts in the gaps between units
+    let c = Compositions::new(9, 4);
+    assert_eq!(c.len(), 56);
+    for (r, parts) in c.enumerate() {
+        let gaps: Vec<usize> = parts.iter().scan(0, |sum, &part| {
+            *sum += part;
+            Some(*sum - 1)
+        }).take(3).collect();
+        assert_eq!(rank(8, &gaps), Some(r));
+        assert_eq!(unrank(8, 3, r), Some(gaps));
+    }
+    assert_eq!(Compositions::new(3, 4).next(), None);
+    assert_eq!(Compositions::weak(3, 0).next(), None);
+    assert_eq!(Compositions::weak(0, 2).collect::<Vec<_>>(), [[0, 0]]);
+
+    // far too many to count, but the first few can still be reached
+    let mut c = Compositions::weak(1 << 40, 10);
+    assert_eq!(c.size_hint(), (usize::MAX, None));
+    assert_eq!(c.nth(3), Some(vec![0, 0, 0, 0, 0, 0, 0, 0, 3, (1 << 40) - 3]));
+
+    // counting takes no table, however big the number or the largest part
+    assert_eq!(count_compositions(1 << 40, 10..=10, ..=5), Some(0));
+    assert_eq!(count_compositions(100, 10..=10, ..=20), Some(342_237_634_221));
+    let mut c = Compositions::with_sizes(1 << 40, 10, ..=1 << 38);
+    assert_eq!(c.remaining(), None);
+    assert_eq!(c.nth(1), Some(vec![0, 0, 0, 0, 0, 1, (1 << 38) - 1, 1 << 38, 1 << 38, 1 << 38]));
+
+    // the count is exact even when the terms of the sum for it do not fit in a u128
+    let mut ways = vec![1u128];
+    for _ in 0..40 {
+        ways = (0..ways.len() + 7).map(|s| ways[s.saturating_sub(7)..(s + 1).min(ways.len())].iter().sum()).collect();
+    }
+    let tally = Tally { h: 7 };
+    assert_eq!(tally.between(40, 140, 140), Some(ways[140]));
+    assert_eq!(tally.between(40, 100, 180), Some(ways[100..=180].iter().sum()));
+}
+
+#[test]
+fn integer_partitions() {
+    fn all(n: usize, most: usize) -> Vec<Vec<usize>> {
+        if n == 0 {
+            return vec![Vec::new()];
+        }
+        let mut ret = Vec::new();
+        for first in 1..=most.min(n) {
+            for mut rest in all(n - first, first) {
+                rest.insert(0, first);
+                ret.push(rest);
+            }
+        }
+        ret
+    }
+
+    for n in 1..=12 {
+        let mut every = all(n, n);
+        every.sort();
+        assert_eq!(IntegerPartitions::new(n).collect::<Vec<_>>(), every);
+        for &(parts, sizes) in &[((2, 4), (1, 3)), ((1, 12), (2, 5)), ((3, 3), (1, 12)), ((5, 12), (1, 12))] {
+            let expected: Vec<&Vec<usize>> = every
+                .iter()
+                .filter(|p| parts.0 <= p.len() && p.len() <= parts.1)
+                .filter(|p| p.iter().all(|&x| sizes.0 <= x && x <= sizes.1))
+                .collect();
+            let p = IntegerPartitions::with_bounds(n, parts.0..=parts.1, sizes.0..=sizes.1);
+            assert_eq!(p.len(), expected.len());
+            assert_eq!(p.clone().collect::<Vec<_>>(), expected.iter().map(|p| p.to_vec()).collect::<Vec<_>>());
+            for (r, partition) in expected.into_iter().enumerate() {
+                assert_eq!(rank_integer_partition(parts.0..=parts.1, sizes.0..=sizes.1, partition), Some(r));
+                let unranked = unrank_integer_partition(n, parts.0..=parts.1, sizes.0..=sizes.1, r);
+                assert_eq!(unranked.as_ref(), Some(partition));
+                assert_eq!(p.clone().nth(r).as_ref(), Some(partition));
+            }
+        }
+    }
+    assert_eq!(IntegerPartitions::new(0).next(), None);
+    assert_eq!(IntegerPartitions::with_bounds(7, 2..=2, 4..).next(), None);
+    assert_eq!(count_integer_partitions(500, .., ..), None);
+    assert_eq!(count_integer_partitions(400, .., ..), Some(6_727_090_051_741_041_926));
+
+    // counting goes one size at a time rather than keeping a table for every size
+    let mut p = IntegerPartitions::new(3000);
+    assert_eq!(p.remaining(), None);
+    let mut ones = vec![1; 2992];
+    ones.splice(..0, [2, 2, 2, 2]);
+    assert_eq!(p.nth(4), Some(ones));
+    assert_eq!(rank_integer_partition(.., .., &[2999, 1]), None);
+}
@@ -2,16 +2,91 @@
 //! A general combination generator that iterates over all possible combinations of a slice of items.
 //!
 //! Note that combinations are different than permutations in that this crate will not generate all possible orderings
-//! of those items.
+//! of those items. For those, see the [`permutations`] module.
 //!
 //! This crate does not check for uniqueness among the items; if this is desired, it is left up to the user to ensure that
-//! the items are unique before passing them to [`CombinationIterator::new`].
+//! the items are unique before passing them to [`CombinationIterator::new`]. To produce each distinct combination of
+//! values once when some items are equal, use [`MultisetCombinations`] instead.
 //! 
+//! The iterators know how many values they have left, so they implement `ExactSizeIterator`, but the counts soon grow
+//! past `usize::MAX`, where `len` panics. Each iterator's `remaining` method returns the count as an `Option` instead,
+//! which is `None` until the count fits (see [`CombinationIterator::remaining`]). Wherever the values can be ranked,
+//! `nth` and `nth_back` skip ahead by unranking rather than generating each value in turn.
+//! 
+//! With the `rayon` feature, a [`CombinationIterator`] also implements rayon's `IntoParallelIterator`, splitting the
+//! combinations between threads by rank (see `ParCombinations`). With the `serde` feature, the position of an
+//! iteration saved as a [`CombinationState`] can be serialized, so a long enumeration can be resumed after a restart.
+//! With the `rand` feature, `random_combination` samples a uniformly random combination without enumerating any.
+//! 
+//! [`CombinationIterator`]: struct.CombinationIterator.html
+//! [`CombinationState`]: struct.CombinationState.html
 //! [`CombinationIterator::new`]: struct.CombinationIterator.html#method.new
+//! [`CombinationIterator::remaining`]: struct.CombinationIterator.html#method.remaining
+//! [`permutations`]: permutations/index.html
+//! [`MultisetCombinations`]: struct.MultisetCombinations.html
+
+mod bits;
+mod cursor;
+mod grouped;
+mod indices;
+mod integers;
+mod multiset;
+mod mutable;
+mod natural;
+mod owned;
+#[cfg(feature = "rayon")]
+mod parallel;
+mod partitions;
+pub mod permutations;
+mod product;
+#[cfg(feature = "rand")]
+mod random;
+mod remaining;
+mod replacement;
+mod revolving_door;
+mod state;
+mod subsets;
+
+pub use bits::{BitCombinations, BitMask, BitSet};
+pub use grouped::{count_grouped, rank_grouped, unrank_grouped, GroupedCombinations};
+pub use indices::{IndexCombinations, MappedCombinations};
+pub use integers::{
+    count_compositions, count_integer_partitions, rank_composition, rank_integer_partition, unrank_composition,
+    unrank_integer_partition, Compositions, IntegerPartitions,
+};
+pub use multiset::{count_multiset, MultisetCombinations};
+pub use mutable::CombinationsMut;
+pub use owned::OwnedCombinations;
+#[cfg(feature = "rayon")]
+pub use parallel::ParCombinations;
+pub use partitions::{
+    bell, count_sized_partitions, rank_sized_partition, stirling2, unrank_sized_partition, SetPartitions,
+    SizedPartitions,
+};
+pub use product::{Product, ProductOrder};
+#[cfg(feature = "rand")]
+pub use random::{random_combination, random_indices};
+pub use replacement::{
+    count_with_replacement, rank_with_replacement, unrank_with_replacement, CombinationsWithReplacement,
+};
+pub use revolving_door::{RevolvingDoor, Swap};
+pub use state::CombinationState;
+pub use subsets::{count_subsets, SubsetOrder, Subsets};
+
+
+use cursor::Cursor;
+use std::iter::FusedIterator;
+use std::ops::{Bound, RangeBounds};
 
 /// Iterates over all possible combinations of items.
 /// 
-/// The combinations are of immutable references to the items.
+/// The combinations are of immutable references to the items. Each call to [`next`] allocates a new `Vec` for the
+/// combination; [`next_slice`] and [`next_into`] avoid that by reusing a buffer.
+/// 
+/// The iterator always knows how many combinations it has left, so [`size_hint`] is exact and it implements
+/// [`ExactSizeIterator`]. If there are more than `usize::MAX` combinations left, [`size_hint`] returns
+/// `(usize::MAX, None)` and [`len`] panics, so use [`remaining`] to get the count when it may be that large. It
+/// returns `None` instead, until enough combinations have been consumed for the count to fit.
 /// 
 /// # Examples
 /// 
@@ -26,19 +101,301 @@
 ///     // [2, 3]
 /// }
 /// ```
+/// 
+/// [`next`]: https://doc.rust-lang.org/std/iter/trait.Iterator.html#tymethod.next
+/// [`next_slice`]: #method.next_slice
+/// [`next_into`]: #method.next_into
+/// [`remaining`]: #method.remaining
+/// [`size_hint`]: https://doc.rust-lang.org/std/iter/trait.Iterator.html#method.size_hint
+/// [`ExactSizeIterator`]: https://doc.rust-lang.org/std/iter/trait.ExactSizeIterator.html
+/// [`len`]: https://doc.rust-lang.org/std/iter/trait.ExactSizeIterator.html#method.len
 #[derive(Debug)]
 pub struct CombinationIterator<'a, T> {
     items: &'a [T],
-    indices: Vec<usize>,
+    cursor: Cursor,
+    buffer: Vec<&'a T>, // reused by next_slice
 }
 
-impl<T> CombinationIterator<'_, T> {
+impl<'a, T> CombinationIterator<'a, T> {
     /// Creates an iterator over combinations of `items` with length `n`.
     /// 
     /// If `n` is 0 or greater than `items.len()`, the iterator will produce no values.
-    pub fn new(items: &[T], n: usize) -> CombinationIterator<T> {
-        let indices = (0..n).collect();
-        CombinationIterator { items, indices }
+    pub fn new(items: &[T], n: usize) -> CombinationIterator<'_, T> {
+        CombinationIterator { items, cursor: Cursor::new(items.len(), n), buffer: Vec::new() }
+    }
+
+    /// Creates an iterator over combinations of `items` with length `n`, starting at the combination with the given
+    /// lexicographic `rank` (see [`rank`]).
+    /// 
+    /// If `rank` is past the last combination, the iterator will produce no values.
+    /// 
+    /// # Examples
+    /// 
+    /// ```
+    /// use gen_combinations::CombinationIterator;
+    /// 
+    /// let items = [1, 2, 3, 4];
+    /// let mut c = CombinationIterator::from_rank(&items, 2, 4);
+    /// assert_eq!(c.next(), Some(vec![&2, &4]));
+    /// assert_eq!(c.next(), Some(vec![&3, &4]));
+    /// assert_eq!(c.next(), None);
+    /// ```
+    /// 
+    /// [`rank`]: fn.rank.html
+    pub fn from_rank(items: &[T], n: usize, rank: usize) -> CombinationIterator<'_, T> {
+        let cursor = match unrank(items.len(), n, rank) {
+            Some(indices) if n > 0 => Cursor::starting_at(items.len(), indices, Order::Lexicographic),
+            _ => Cursor::empty(items.len(), n, Order::Lexicographic),
+        };
+        CombinationIterator { items, cursor, buffer: Vec::new() }
+    }
+
+    /// Creates an iterator over the combinations of `items` with length `n` whose lexicographic ranks (see [`rank`])
+    /// are in the range `ranks`.
+    /// 
+    /// The combinations are a contiguous slice of those produced by [`new`], found by unranking both ends. Ranks past
+    /// the last combination are ignored, so an unbounded end runs to the last combination even if there are more than
+    /// `usize::MAX` of them.
+    /// 
+    /// # Examples
+    /// 
+    /// ```
+    /// use gen_combinations::CombinationIterator;
+    /// 
+    /// let items = [1, 2, 3, 4];
+    /// let middle: Vec<_> = CombinationIterator::range(&items, 2, 2..4).collect();
+    /// assert_eq!(middle, [[&1, &4], [&2, &3]]);
+    /// assert_eq!(CombinationIterator::range(&items, 2, 4..).len(), 2);
+    /// ```
+    /// 
+    /// [`rank`]: fn.rank.html
+    /// [`new`]: #method.new
+    pub fn range<R: RangeBounds<usize>>(items: &[T], n: usize, ranks: R) -> CombinationIterator<'_, T> {
+        let len = items.len();
+        let start = match ranks.start_bound() {
+            Bound::Included(&start) => start as u128,
+            Bound::Excluded(&start) => start as u128 + 1,
+            Bound::Unbounded => 0,
+        };
+        // the rank after the last combination, or None if there are too many to count and the range is unbounded
+        let count = if n == 0 || n > len { Some(0) } else { wide_binomial(len, n) };
+        let end = match ranks.end_bound() {
+            Bound::Included(&end) => Some(end as u128 + 1),
+            Bound::Excluded(&end) => Some(end as u128),
+            Bound::Unbounded => count,
+        };
+        let end = match (end, count) {
+            (Some(end), Some(count)) => Some(end.min(count)),
+            (end, _) => end,
+        };
+        if end.is_some_and(|end| start >= end) {
+            let cursor = Cursor::empty(len, n, Order::Lexicographic);
+            return CombinationIterator { items, cursor, buffer: Vec::new() };
+        }
+        let mut front = vec![0; n];
+        unrank_into(&mut front, 0, len, start);
+        let back = match end {
+            Some(end) => {
+                let mut back = vec![0; n];
+                unrank_into(&mut back, 0, len, end - 1);
+                back
+            }
+            None => (len - n..len).collect(),
+        };
+        let cursor = Cursor::between(len, front, back, Order::Lexicographic);
+        CombinationIterator { items, cursor, buffer: Vec::new() }
+    }
+
+    /// Creates an iterator over the combinations of `items` in lexicographic order from the one with the indices
+    /// `start` up to but excluding the one with the indices `end`, or to the last combination if `end` is `None`.
+    /// 
+    /// The length of the combinations is that of `start`. If `start` or `end` is not a strictly increasing list of
+    /// indices less than `items.len()`, if they differ in length, or if `end` does not come after `start`, the iterator
+    /// will produce no values.
+    /// 
+    /// # Examples
+    /// 
+    /// ```
+    /// use gen_combinations::CombinationIterator;
+    /// 
+    /// let items: Vec<usize> = (0..6).collect();
+    /// let mut c = CombinationIterator::between(&items, &[0, 3, 5], Some(&[1, 2, 3]));
+    /// assert_eq!(c.next(), Some(vec![&0, &3, &5]));
+    /// assert_eq!(c.next(), Some(vec![&0, &4, &5]));
+    /// assert_eq!(c.next(), None);
+    /// assert_eq!(CombinationIterator::between(&items, &[3, 4, 5], None).len(), 1);
+    /// ```
+    pub fn between(items: &'a [T], start: &[usize], end: Option<&[usize]>) -> CombinationIterator<'a, T> {
+        let len = items.len();
+        let valid = |indices: &[usize]| {
+            indices.len() == start.len()
+                && indices.windows(2).all(|w| w[0] < w[1])
+                && indices.last().is_some_and(|&i| i < len)
+        };
+        let back = match end {
+            Some(end) if valid(start) && valid(end) => {
+                let mut back = end.to_vec();
+                // the first combination has nothing before it
+                if cursor::predecessor(&mut back, len) {
+                    Some(back)
+                } else {
+                    None
+                }
+            }
+            None if valid(start) => Some((len - start.len()..len).collect()),
+            _ => None,
+        };
+        let cursor = match back {
+            Some(back) => Cursor::between(len, start.to_vec(), back, Order::Lexicographic),
+            None => Cursor::empty(len, start.len(), Order::Lexicographic),
+        };
+        CombinationIterator { items, cursor, buffer: Vec::new() }
+    }
+
+    /// Restricts the iterator to the `i`th of `m` shards of the combinations it has left, numbering from 0.
+    /// 
+    /// The shards are contiguous and as equal in size as possible, with the first ones taking any extra combinations,
+    /// so running shards 0 to `m - 1` in turn produces each combination exactly once and in the same order as the
+    /// iterator would have. Each shard is found by unranking, without generating the combinations before it.
+    /// 
+    /// # Panics
+    /// 
+    /// Panics if `i` is not less than `m`, or if there are more than `usize::MAX` combinations left.
+    /// 
+    /// # Examples
+    /// 
+    /// ```
+    /// use gen_combinations::CombinationIterator;
+    /// 
+    /// let items = [1, 2, 3, 4, 5];
+    /// let shards: Vec<usize> = (0..3).map(|i| CombinationIterator::new(&items, 2).shard(i, 3).len()).collect();
+    /// assert_eq!(shards, [4, 3, 3]);
+    /// let mut last = CombinationIterator::new(&items, 2).shard(2, 3);
+    /// assert_eq!(last.next(), Some(vec![&3, &4]));
+    /// ```
+    pub fn shard(mut self, i: usize, m: usize) -> CombinationIterator<'a, T> {
+        assert!(i < m, "shard {} out of range for {} shards", i, m);
+        let len = self.cursor.remaining().expect("more than usize::MAX combinations");
+        let (size, extra) = (len / m, len % m);
+        let start = i * size + i.min(extra);
+        let end = start + size + usize::from(i < extra);
+        self.cursor.skip_back(len - end);
+        self.cursor.skip_front(start);
+        self
+    }
+
+    /// Creates an iterator over combinations of `items` with length `n` in the given order.
+    /// 
+    /// To start partway through, use [`skip`], which jumps ahead without generating the combinations it skips.
+    /// 
+    /// # Examples
+    /// 
+    /// ```
+    /// use gen_combinations::{CombinationIterator, Order};
+    /// 
+    /// let items = [1, 2, 3, 4];
+    /// let colex: Vec<_> = CombinationIterator::with_order(&items, 2, Order::Colexicographic).collect();
+    /// assert_eq!(colex, [[&1, &2], [&1, &3], [&2, &3], [&1, &4], [&2, &4], [&3, &4]]);
+    /// ```
+    /// 
+    /// [`skip`]: https://doc.rust-lang.org/std/iter/trait.Iterator.html#method.skip
+    pub fn with_order(items: &[T], n: usize, order: Order) -> CombinationIterator<'_, T> {
+        let cursor = Cursor::starting_at(items.len(), (0..n).collect(), order);
+        CombinationIterator { items, cursor, buffer: Vec::new() }
+    }
+
+    /// Returns the order in which the combinations are produced.
+    pub fn order(&self) -> Order {
+        self.cursor.order()
+    }
+
+    /// Returns the number of combinations left, or `None` if it does not fit in a `usize` and [`len`] would panic.
+    /// 
+    /// # Examples
+    /// 
+    /// ```
+    /// use gen_combinations::CombinationIterator;
+    /// 
+    /// // there are 28_453_041_475_240_576_740 combinations, more than fit in a 64-bit usize
+    /// let items: Vec<usize> = (0..68).collect();
+    /// let mut c = CombinationIterator::new(&items, 34);
+    /// assert_eq!(c.remaining(), None);
+    /// c.nth(usize::MAX - 1);
+    /// assert_eq!(c.remaining(), Some(10_006_297_401_531_025_125));
+    /// ```
+    /// 
+    /// [`len`]: https://doc.rust-lang.org/std/iter/trait.ExactSizeIterator.html#method.len
+    pub fn remaining(&self) -> Option<usize> {
+        self.cursor.remaining()
+    }
+
+    /// Returns the indices into `items` of the next combination from the front, or an empty slice if there are no
+    /// combinations left.
+    /// 
+    /// # Examples
+    /// 
+    /// ```
+    /// use gen_combinations::CombinationIterator;
+    /// 
+    /// let items = ['a', 'b', 'c'];
+    /// let mut c = CombinationIterator::new(&items, 2);
+    /// c.next();
+    /// assert_eq!(c.indices(), &[0, 2]);
+    /// ```
+    pub fn indices(&self) -> &[usize] {
+        self.cursor.front()
+    }
+
+    /// Advances the iterator and returns the next combination, like [`next`], but in a buffer owned by the iterator
+    /// instead of a newly allocated `Vec`.
+    /// 
+    /// # Examples
+    /// 
+    /// ```
+    /// use gen_combinations::CombinationIterator;
+    /// 
+    /// let items = [1, 2, 3];
+    /// let mut c = CombinationIterator::new(&items, 2);
+    /// let mut sums = Vec::new();
+    /// while let Some(combo) = c.next_slice() {
+    ///     sums.push(combo.iter().copied().sum::<i32>());
+    /// }
+    /// assert_eq!(sums, [3, 4, 5]);
+    /// ```
+    /// 
+    /// [`next`]: https://doc.rust-lang.org/std/iter/trait.Iterator.html#tymethod.next
+    pub fn next_slice(&mut self) -> Option<&[&'a T]> {
+        let mut buffer = std::mem::take(&mut self.buffer);
+        let more = self.next_into(&mut buffer);
+        self.buffer = buffer;
+        if more {
+            Some(&self.buffer)
+        } else {
+            None
+        }
+    }
+
+    /// Advances the iterator and writes the next combination into `combo`, replacing its contents.
+    /// 
+    /// Returns `false`, leaving `combo` unchanged, if there are no combinations left.
+    pub fn next_into(&mut self, combo: &mut Vec<&'a T>) -> bool {
+        let items = self.items;
+        self.cursor
+            .next_front(|indices| {
+                combo.clear();
+                combo.extend(indices.iter().map(|i| &items[*i]));
+            })
+            .is_some()
+    }
+}
+
+impl<T> Clone for CombinationIterator<'_, T> {
+    fn clone(&self) -> Self {
+        CombinationIterator {
+            items: self.items,
+            cursor: self.cursor.clone(),
+            buffer: Vec::new(),
+        }
     }
 }
 
@@ -46,22 +403,299 @@ impl<'a, T> Iterator for CombinationIterator<'a, T> {
     type Item = Vec<&'a T>;
 
     fn next(&mut self) -> Option<Vec<&'a T>> {
-        if self.indices.is_empty() || self.indices.len() > self.items.len() {
-            None
-        } else {
-            let ret = self.indices.iter().map(|i| &(self.items[*i])).collect();
-            for i in (0..self.indices.len()).rev() {
-                if self.indices[i] < self.items.len() - (self.indices.len() - i) {
-                    self.indices[i] += 1;
-                    for j in i..self.indices.len() {
-                        self.indices[j] = self.indices[i] + (j - i);
-                    }
-                    return Some(ret);
+        let items = self.items;
+        self.cursor.next_front(|combo| combo.iter().map(|i| &items[*i]).collect())
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.cursor.size_hint()
+    }
+
+    fn count(self) -> usize {
+        self.cursor.len()
+    }
+
+    fn nth(&mut self, n: usize) -> Option<Vec<&'a T>> {
+        let items = self.items;
+        self.cursor.nth_front(n, |combo| combo.iter().map(|i| &items[*i]).collect())
+    }
+}
+
+impl<'a, T> DoubleEndedIterator for CombinationIterator<'a, T> {
+    fn next_back(&mut self) -> Option<Vec<&'a T>> {
+        let items = self.items;
+        self.cursor.next_back(|combo| combo.iter().map(|i| &items[*i]).collect())
+    }
+
+    fn nth_back(&mut self, n: usize) -> Option<Vec<&'a T>> {
+        let items = self.items;
+        self.cursor.nth_back(n, |combo| combo.iter().map(|i| &items[*i]).collect())
+    }
+}
+
+impl<T> ExactSizeIterator for CombinationIterator<'_, T> {}
+
+impl<T> FusedIterator for CombinationIterator<'_, T> {}
+
+/// An order in which to produce combinations.
+/// 
+/// Both orders compare combinations by their indices into the items, which are always kept in increasing order
+/// within each combination.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Order {
+    /// Combinations are ordered by their first index, then by their second, and so on. This is the default.
+    #[default]
+    Lexicographic,
+    /// Combinations are ordered by their last index, then by their second-to-last, and so on.
+    /// 
+    /// In this order, the first `C(m, k)` combinations of any length `k` only use the first `m` items, so appending
+    /// items to a slice only adds combinations to the end. The ranks are also independent of the number of items.
+    Colexicographic,
+}
+
+impl Order {
+    /// Returns the rank of a combination in this order, given as the strictly increasing `indices` of its items out
+    /// of `n` total.
+    /// 
+    /// The first combination, `[0, 1, ..., k - 1]`, has rank 0. Returns `None` if `indices` is not strictly
+    /// increasing, if any index is not less than `n`, or if the rank does not fit in a `usize`.
+    /// 
+    /// # Examples
+    /// 
+    /// ```
+    /// use gen_combinations::Order;
+    /// 
+    /// assert_eq!(Order::Lexicographic.rank(5, &[0, 1, 4]), Some(2));
+    /// assert_eq!(Order::Colexicographic.rank(5, &[0, 1, 4]), Some(4));
+    /// ```
+    pub fn rank(self, n: usize, indices: &[usize]) -> Option<usize> {
+        match self {
+            Order::Lexicographic => rank(n, indices),
+            Order::Colexicographic => {
+                if indices.windows(2).any(|w| w[0] >= w[1]) || indices.last().is_some_and(|&i| i >= n) {
+                    return None;
+                }
+                narrow(wide_colex_rank(indices)?)
+            }
+        }
+    }
+
+    /// Returns the indices of the combination of `k` out of `n` items with the given `rank` in this order.
+    /// 
+    /// This is the inverse of [`rank`]. Returns `None` if there are not more than `rank` such combinations.
+    /// 
+    /// # Examples
+    /// 
+    /// ```
+    /// use gen_combinations::Order;
+    /// 
+    /// assert_eq!(Order::Colexicographic.unrank(5, 3, 4), Some(vec![0, 1, 4]));
+    /// ```
+    /// 
+    /// [`rank`]: #method.rank
+    pub fn unrank(self, n: usize, k: usize, rank: usize) -> Option<Vec<usize>> {
+        match self {
+            Order::Lexicographic => unrank(n, k, rank),
+            Order::Colexicographic => {
+                if k > n || binomial(n, k).is_some_and(|count| rank >= count) {
+                    return None;
+                }
+                let mut indices = vec![0; k];
+                colex_unrank_into(&mut indices, rank as u128);
+                Some(indices)
+            }
+        }
+    }
+}
+
+/// Returns the lexicographic rank of a combination, given as the strictly increasing `indices` of its items out of `n`
+/// total.
+/// 
+/// The first combination, `[0, 1, ..., k - 1]`, has rank 0. Returns `None` if `indices` is not strictly increasing, if
+/// any index is not less than `n`, or if the rank does not fit in a `usize`. For other orders, see [`Order::rank`].
+/// 
+/// # Examples
+/// 
+/// ```
+/// assert_eq!(gen_combinations::rank(5, &[0, 1, 2]), Some(0));
+/// assert_eq!(gen_combinations::rank(5, &[1, 3, 4]), Some(8));
+/// assert_eq!(gen_combinations::rank(5, &[3, 1]), None);
+/// ```
+/// 
+/// [`Order::rank`]: enum.Order.html#method.rank
+pub fn rank(n: usize, indices: &[usize]) -> Option<usize> {
+    if indices.windows(2).any(|w| w[0] >= w[1]) || indices.last().is_some_and(|&i| i >= n) {
+        return None;
+    }
+    narrow(wide_rank(n, indices)?)
+}
+
+/// Returns the lexicographic rank of the strictly increasing `indices` out of `n`, or `None` if it does not fit in a
+/// `u128`.
+fn wide_rank(n: usize, indices: &[usize]) -> Option<u128> {
+    let k = indices.len();
+    let mut rank = 0u128;
+    let mut lo = 0;
+    for (i, &c) in indices.iter().enumerate() {
+        // every combination that agrees up to position i but has a smaller item there comes first
+        if c > lo {
+            let before = wide_binomial(n - lo, k - i)? - wide_binomial(n - c, k - i)?;
+            rank = rank.checked_add(before)?;
+        }
+        lo = c + 1;
+    }
+    Some(rank)
+}
+
+/// Returns the indices of the combination of `k` out of `n` items with the given lexicographic `rank`.
+/// 
+/// This is the inverse of [`rank`]. Returns `None` if there are not more than `rank` such combinations.
+/// 
+/// # Examples
+/// 
+/// ```
+/// assert_eq!(gen_combinations::unrank(5, 3, 8), Some(vec![1, 3, 4]));
+/// assert_eq!(gen_combinations::unrank(5, 3, 10), None);
+/// ```
+/// 
+/// [`rank`]: fn.rank.html
+pub fn unrank(n: usize, k: usize, rank: usize) -> Option<Vec<usize>> {
+    if k > n || binomial(n, k).is_some_and(|count| rank >= count) {
+        return None;
+    }
+    let mut indices = vec![0; k];
+    unrank_into(&mut indices, 0, n, rank as u128);
+    Some(indices)
+}
+
+/// Returns the binomial coefficient `C(n, k)`, the number of combinations of `k` out of `n` items, or `None` if it does
+/// not fit in a `usize`.
+/// 
+/// Note that `C(n, 0)` is 1, whereas a [`CombinationIterator`] with length 0 produces no values.
+/// 
+/// # Examples
+/// 
+/// ```
+/// assert_eq!(gen_combinations::binomial(5, 2), Some(10));
+/// assert_eq!(gen_combinations::binomial(2, 5), Some(0));
+/// assert_eq!(gen_combinations::binomial(200, 100), None);
+/// ```
+/// 
+/// [`CombinationIterator`]: struct.CombinationIterator.html
+pub fn binomial(n: usize, k: usize) -> Option<usize> {
+    narrow(wide_binomial(n, k)?)
+}
+
+/// Returns `C(n, k)` as a `u128`, or `None` if it does not fit.
+fn wide_binomial(n: usize, k: usize) -> Option<u128> {
+    if k > n {
+        return Some(0);
+    }
+    let k = k.min(n - k);
+    let mut ret = 1u128;
+    for i in 0..k {
+        // C(n, i + 1) = C(n, i) * (n - i) / (i + 1), split up so that nothing overflows unless C(n, i + 1) does
+        let (q, r) = (ret / (i + 1) as u128, ret % (i + 1) as u128);
+        let m = (n - i) as u128;
+        ret = q.checked_mul(m)?.checked_add(r * m / (i + 1) as u128)?;
+    }
+    Some(ret)
+}
+
+fn narrow(x: u128) -> Option<usize> {
+    if x > usize::MAX as u128 {
+        None
+    } else {
+        Some(x as usize)
+    }
+}
+
+/// Returns the co-lexicographic rank of the strictly increasing `indices`, or `None` if it does not fit in a `u128`.
+fn wide_colex_rank(indices: &[usize]) -> Option<u128> {
+    // the combinations before are those whose largest differing item is smaller
+    indices.iter().enumerate().try_fold(0u128, |rank, (i, &c)| rank.checked_add(wide_binomial(c, i + 1)?))
+}
+
+/// Fills `indices` with the combination of `indices.len()` items that has the given co-lexicographic rank.
+fn colex_unrank_into(indices: &mut [usize], mut rank: u128) {
+    for i in (0..indices.len()).rev() {
+        let j = i + 1;
+        // find the largest item `c` with C(c, j) <= rank, first by galloping up from the smallest possible one
+        let fits = |c: usize| wide_binomial(c, j).is_some_and(|count| count <= rank);
+        let mut lo = i;
+        let mut step = 1;
+        while let Some(next) = lo.checked_add(step).filter(|&next| fits(next)) {
+            lo = next;
+            step *= 2;
+        }
+        let mut hi = lo.saturating_add(step);
+        while hi - lo > 1 {
+            let mid = lo + (hi - lo) / 2;
+            if fits(mid) {
+                lo = mid;
+            } else {
+                hi = mid;
+            }
+        }
+        rank -= wide_binomial(lo, j).unwrap();
+        indices[i] = lo;
+    }
+}
+
+/// Fills `indices` with the combination of `indices.len()` items out of `lo..n` that has the given lexicographic rank.
+/// 
+/// `rank` must be less than the number of such combinations.
+fn unrank_into(indices: &mut [usize], lo: usize, n: usize, mut rank: u128) {
+    let k = indices.len();
+    let mut lo = lo;
+    for (i, slot) in indices.iter_mut().enumerate() {
+        let j = k - i;
+        // if there are too many combinations to count, the ones starting with `lo` alone are more than any rank
+        if let Some(total) = wide_binomial(n - lo, j) {
+            // find the first item such that more than `rank` combinations start at or before it
+            let before = |x: usize| total - wide_binomial(n - x, j).unwrap();
+            let (mut a, mut b) = (lo, n - j);
+            while a < b {
+                let mid = a + (b - a) / 2;
+                if before(mid + 1) > rank {
+                    b = mid;
+                } else {
+                    a = mid + 1;
                 }
             }
-            self.indices.clear(); // next iteration will see that self.indices is empty and stop
-            Some(ret)
+            rank -= before(a);
+            lo = a;
+        }
+        *slot = lo;
+        lo += 1;
+    }
+}
+
+/// Fills `indices` with the combination of `indices.len()` items out of `lo..n` that comes `rank` places before the
+/// last one in lexicographic order.
+/// 
+/// `rank` must be less than the number of such combinations.
+fn unrank_from_end(indices: &mut [usize], lo: usize, n: usize, rank: usize) {
+    let k = indices.len();
+    let mut lo = lo;
+    let mut rank = rank as u128;
+    for (i, slot) in indices.iter_mut().enumerate() {
+        let j = k - i;
+        // find the last item such that more than `rank` combinations start at or after it
+        let from = |x: usize| wide_binomial(n - x, j);
+        let (mut a, mut b) = (lo, n - j);
+        while a < b {
+            let mid = b - (b - a) / 2;
+            if from(mid).is_none_or(|count| count > rank) {
+                a = mid;
+            } else {
+                b = mid - 1;
+            }
         }
+        rank -= from(a + 1).unwrap();
+        *slot = a;
+        lo = a + 1;
     }
 }
 
@@ -111,3 +745,295 @@ fn misuse_arguments() {
     let mut c = CombinationIterator::new(&items, 0);
     assert_eq!(c.next(), None);
 }
+
+#[test]
+fn rank_and_unrank_agree_with_iteration() {
+    let items = [0, 1, 2, 3, 4, 5, 6];
+    for (r, combo) in CombinationIterator::new(&items, 4).enumerate() {
+        let indices: Vec<usize> = combo.into_iter().copied().collect();
+        assert_eq!(rank(items.len(), &indices), Some(r));
+        assert_eq!(unrank(items.len(), 4, r), Some(indices));
+    }
+    assert_eq!(unrank(items.len(), 4, 35), None);
+}
+
+#[test]
+fn rank_and_unrank_huge() {
+    // C(200, 100) is far larger than a usize, but ranks near either end are still representable
+    let first: Vec<usize> = (0..100).collect();
+    assert_eq!(rank(200, &first), Some(0));
+    assert_eq!(unrank(200, 100, 0), Some(first));
+    let mut second: Vec<usize> = (0..100).collect();
+    second[99] = 100;
+    assert_eq!(rank(200, &second), Some(1));
+    assert_eq!(unrank(200, 100, 1), Some(second));
+    let mut late: Vec<usize> = (0..100).collect();
+    late[0] = 1;
+    assert_eq!(rank(200, &late), None);
+}
+
+#[test]
+fn nth_and_from_rank() {
+    let items: Vec<usize> = (0..9).collect();
+    let all: Vec<_> = CombinationIterator::new(&items, 4).collect();
+    for skip in 0..all.len() + 2 {
+        let mut c = CombinationIterator::new(&items, 4);
+        assert_eq!(c.nth(skip), all.get(skip).cloned());
+        assert_eq!(c.next(), all.get(skip + 1).cloned());
+
+        let mut c = CombinationIterator::from_rank(&items, 4, skip);
+        assert_eq!(c.next(), all.get(skip).cloned());
+    }
+
+    let mut c = CombinationIterator::new(&items, 4);
+    assert_eq!(c.nth(3), all.get(3).cloned());
+    assert_eq!(c.nth(50), all.get(54).cloned());
+    assert_eq!(c.nth(100), None);
+    assert_eq!(c.next(), None);
+}
+
+#[test]
+fn exact_size() {
+    let items: Vec<usize> = (0..7).collect();
+    let mut c = CombinationIterator::new(&items, 3);
+    assert_eq!(c.len(), 35);
+    c.next();
+    assert_eq!(c.size_hint(), (34, Some(34)));
+    c.nth(30);
+    assert_eq!(c.len(), 3);
+    assert_eq!(c.by_ref().count(), 3);
+    assert_eq!(c.len(), 0);
+
+    assert_eq!(CombinationIterator::from_rank(&items, 3, 10).len(), 25);
+    assert_eq!(CombinationIterator::new(&items, 0).len(), 0);
+    assert_eq!(CombinationIterator::new(&items, 8).len(), 0);
+}
+
+#[test]
+fn size_hint_overflow() {
+    let items: Vec<usize> = (0..200).collect();
+    let mut c = CombinationIterator::new(&items, 100);
+    assert_eq!(c.size_hint(), (usize::MAX, None));
+    c.next();
+    assert_eq!(c.size_hint(), (usize::MAX, None));
+
+    // close to the end, the count fits again
+    let mut indices: Vec<usize> = (100..200).collect();
+    indices[0] = 98;
+    let cursor = Cursor::starting_at(items.len(), indices, Order::Lexicographic);
+    let mut c = CombinationIterator { items: &items, cursor, buffer: Vec::new() };
+    assert_eq!(c.len(), 102);
+    c.next();
+    assert_eq!(c.len(), 101);
+}
+
+#[test]
+fn double_ended() {
+    let items: Vec<usize> = (0..7).collect();
+    let all: Vec<_> = CombinationIterator::new(&items, 3).collect();
+    let reversed: Vec<_> = CombinationIterator::new(&items, 3).rev().collect();
+    assert_eq!(reversed, all.iter().rev().cloned().collect::<Vec<_>>());
+
+    for split in 0..=all.len() {
+        let mut c = CombinationIterator::new(&items, 3);
+        let mut front: Vec<_> = c.by_ref().take(split).collect();
+        let back: Vec<_> = c.by_ref().rev().collect();
+        assert_eq!(c.next(), None);
+        assert_eq!(c.next_back(), None);
+        front.extend(back.into_iter().rev());
+        assert_eq!(front, all);
+    }
+}
+
+#[test]
+fn nth_back() {
+    let items: Vec<usize> = (0..8).collect();
+    let all: Vec<_> = CombinationIterator::new(&items, 3).collect();
+    for skip in 0..all.len() + 2 {
+        let mut c = CombinationIterator::new(&items, 3);
+        assert_eq!(c.nth_back(skip), all.iter().rev().nth(skip).cloned());
+        assert_eq!(c.len(), all.len().saturating_sub(skip + 1));
+    }
+
+    let mut c = CombinationIterator::new(&items, 3);
+    assert_eq!(c.nth(10), Some(all[10].clone()));
+    assert_eq!(c.nth_back(40), Some(all[15].clone()));
+    assert_eq!(c.clone().collect::<Vec<_>>(), all[11..15].to_vec());
+    assert_eq!(c.nth_back(3), Some(all[11].clone()));
+    assert_eq!(c.next(), None);
+}
+
+#[test]
+fn lending() {
+    let items = [1, 2, 3, 4];
+    let all: Vec<_> = CombinationIterator::new(&items, 2).collect();
+    let mut c = CombinationIterator::new(&items, 2);
+    let mut lent = Vec::new();
+    assert_eq!(c.indices(), &[0, 1]);
+    while let Some(combo) = c.next_slice() {
+        lent.push(combo.to_vec());
+    }
+    assert_eq!(lent, all);
+    assert_eq!(c.indices(), &[] as &[usize]);
+
+    let mut c = CombinationIterator::new(&items, 3);
+    let mut combo = Vec::new();
+    assert!(c.next_into(&mut combo));
+    assert_eq!(combo, [&1, &2, &3]);
+    c.next_back();
+    assert!(c.next_into(&mut combo));
+    assert_eq!(combo, [&1, &2, &4]);
+    assert!(c.next_into(&mut combo));
+    assert!(!c.next_into(&mut combo));
+    assert_eq!(combo, [&1, &3, &4]);
+}
+
+#[test]
+fn colexicographic_order() {
+    let items: Vec<usize> = (0..7).collect();
+    let mut expected: Vec<Vec<&usize>> = CombinationIterator::new(&items, 3).collect();
+    expected.sort_by(|a, b| a.iter().rev().cmp(b.iter().rev()));
+    let colex: Vec<_> = CombinationIterator::with_order(&items, 3, Order::Colexicographic).collect();
+    assert_eq!(colex, expected);
+    let reversed: Vec<_> = CombinationIterator::with_order(&items, 3, Order::Colexicographic).rev().collect();
+    assert_eq!(reversed, expected.iter().rev().cloned().collect::<Vec<_>>());
+
+    for (r, combo) in expected.iter().enumerate() {
+        let indices: Vec<usize> = combo.iter().copied().copied().collect();
+        assert_eq!(Order::Colexicographic.rank(7, &indices), Some(r));
+        assert_eq!(Order::Colexicographic.unrank(7, 3, r), Some(indices));
+
+        let mut c = CombinationIterator::with_order(&items, 3, Order::Colexicographic);
+        assert_eq!(c.nth(r).as_ref(), Some(combo));
+        assert_eq!(c.len(), expected.len() - r - 1);
+        let mut c = CombinationIterator::with_order(&items, 3, Order::Colexicographic);
+        assert_eq!(c.nth_back(r).as_ref(), expected.iter().rev().nth(r));
+    }
+    assert_eq!(Order::Colexicographic.unrank(7, 3, 35), None);
+}
+
+#[test]
+fn ranges_and_between() {
+    let items: Vec<usize> = (0..8).collect();
+    let all: Vec<Vec<usize>> = CombinationIterator::new(&items, 3).map(|c| c.into_iter().copied().collect()).collect();
+    for start in 0..=all.len() + 1 {
+        for end in start..=all.len() + 1 {
+            let expected = &all[start.min(all.len())..end.min(all.len())];
+            let c = CombinationIterator::range(&items, 3, start..end);
+            assert_eq!(c.len(), expected.len());
+            assert!(c.map(|c| c.into_iter().copied().collect::<Vec<_>>()).eq(expected.iter().cloned()));
+            if end < all.len() {
+                let c = CombinationIterator::between(&items, &all[start.min(all.len() - 1)], Some(&all[end]));
+                assert_eq!(c.len(), end.saturating_sub(start.min(all.len() - 1)));
+            }
+        }
+        let from = CombinationIterator::range(&items, 3, start..);
+        assert_eq!(from.len(), all.len().saturating_sub(start));
+    }
+    assert_eq!(CombinationIterator::range(&items, 3, ..=2).len(), 3);
+    assert_eq!(CombinationIterator::range(&items, 0, ..).len(), 0);
+    assert_eq!(CombinationIterator::between(&items, &[5, 6, 7], None).len(), 1);
+    assert_eq!(CombinationIterator::between(&items, &[0, 1, 2], Some(&[0, 1, 2])).len(), 0);
+    assert_eq!(CombinationIterator::between(&items, &[0, 2, 1], None).len(), 0);
+    assert_eq!(CombinationIterator::between(&items, &[0, 1], Some(&[0, 1, 2])).len(), 0);
+    assert_eq!(CombinationIterator::between(&items, &[0, 1, 8], None).len(), 0);
+
+    // ranges far into an enumeration too large to count
+    let items: Vec<usize> = (0..200).collect();
+    let mut c = CombinationIterator::range(&items, 100, 1..3);
+    assert_eq!(c.len(), 2);
+    assert_eq!(c.next().map(|c| *c[99]), Some(100));
+    let mut c = CombinationIterator::range(&items, 100, usize::MAX..);
+    assert_eq!(c.size_hint(), (usize::MAX, None));
+    assert_eq!(c.next_back().map(|c| *c[0]), Some(100));
+}
+
+#[test]
+fn shards() {
+    let items: Vec<usize> = (0..9).collect();
+    let all: Vec<_> = CombinationIterator::new(&items, 4).collect();
+    for m in 1..=all.len() + 3 {
+        let shards: Vec<Vec<_>> = (0..m).map(|i| CombinationIterator::new(&items, 4).shard(i, m).collect()).collect();
+        let sizes: Vec<usize> = shards.iter().map(|s| s.len()).collect();
+        assert!(sizes.windows(2).all(|w| w[0] >= w[1] && w[0] - w[1] <= 1));
+        assert_eq!(shards.concat(), all);
+    }
+    let mut c = CombinationIterator::with_order(&items, 4, Order::Colexicographic);
+    c.nth(10);
+    let rest: Vec<_> = c.clone().collect();
+    let sharded: Vec<_> = (0..4).flat_map(|i| c.clone().shard(i, 4)).collect();
+    assert_eq!(sharded, rest);
+}
+
+#[test]
+fn colex_skips_past_u128_ranks() {
+    let items: Vec<usize> = (0..200).collect();
+    let reflect = |indices: &[usize]| -> Vec<usize> { indices.iter().rev().map(|&i| 199 - i).collect() };
+
+    // near the end the co-lexicographic ranks do not fit, but the reflections are near the lexicographic start
+    let mut c = CombinationIterator::with_order(&items, 100, Order::Colexicographic);
+    let expected = reflect(&unrank(200, 100, 1 << 40).unwrap());
+    assert_eq!(c.nth_back(1 << 40).unwrap(), expected.iter().collect::<Vec<_>>());
+    assert_eq!(c.remaining(), None);
+    let cursor = Cursor::starting_at(200, reflect(&unrank(200, 100, 10).unwrap()), Order::Colexicographic);
+    assert_eq!(cursor.remaining(), Some(11));
+
+    // in the middle neither rank fits
+    let middle: Vec<usize> = (0..200).step_by(2).collect();
+    let cursor = Cursor::starting_at(200, middle.clone(), Order::Colexicographic);
+    let mut c = CombinationIterator { items: &items, cursor, buffer: Vec::new() };
+    let mut stepped = c.clone();
+    for _ in 0..1000 {
+        stepped.next();
+    }
+    assert_eq!(c.nth(1000), stepped.next());
+    let cursor = Cursor::between(200, (0..100).collect(), middle, Order::Colexicographic);
+    let mut c = CombinationIterator { items: &items, cursor, buffer: Vec::new() };
+    let mut stepped = c.clone();
+    for _ in 0..1000 {
+        stepped.next_back();
+    }
+    assert_eq!(c.nth_back(1000), stepped.next_back());
+}
+
+#[test]
+fn size_hint_until_the_count_fits() {
+    let items: Vec<usize> = (0..68).collect();
+    let total = 28_453_041_475_240_576_740u128;
+    for from_back in [false, true] {
+        let mut c = CombinationIterator::new(&items, 34);
+        // skip all but usize::MAX + 1 of them, which is still too many
+        let skip = (total - usize::MAX as u128 - 2) as usize;
+        if from_back {
+            c.nth_back(skip);
+        } else {
+            c.nth(skip);
+        }
+        assert_eq!((c.remaining(), c.size_hint()), (None, (usize::MAX, None)));
+        c.next();
+        assert_eq!((c.remaining(), c.size_hint()), (Some(usize::MAX), (usize::MAX, Some(usize::MAX))));
+        assert_eq!(c.len(), usize::MAX);
+        c.next_back();
+        assert_eq!((c.remaining(), c.size_hint()), (Some(usize::MAX - 1), (usize::MAX - 1, Some(usize::MAX - 1))));
+    }
+}
+
+#[test]
+fn remaining_past_usize() {
+    let items: Vec<usize> = (0..68).collect();
+    let mut c = CombinationIterator::new(&items, 34);
+    assert_eq!((c.remaining(), c.size_hint()), (None, (usize::MAX, None)));
+    c.nth(usize::MAX - 1);
+    assert_eq!(c.remaining(), Some(10_006_297_401_531_025_125));
+    assert_eq!(c.len(), 10_006_297_401_531_025_125);
+
+    // a count too large even for a u128 is not recounted at every step
+    let items: Vec<usize> = (0..200).collect();
+    let mut c = CombinationIterator::new(&items, 100);
+    for _ in 0..100_000 {
+        c.next_slice();
+    }
+    c.nth_back(1000);
+    assert_eq!(c.remaining(), None);
+    assert_eq!(c.indices(), &unrank(200, 100, 100_000).unwrap()[..]);
+}
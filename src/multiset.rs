@@ -0,0 +1,332 @@
+use crate::narrow;
+use crate::remaining::Remaining;
+use std::iter::FusedIterator;
+
+/// Iterates over all distinct combinations of items that may contain duplicates.
+/// 
+/// Unlike [`CombinationIterator`], which treats every item as distinct, this produces each combination of values
+/// exactly once, no matter how many equal items it could be made from. Equal items are grouped together, with the
+/// groups in the order their first items appear, and combinations are produced in lexicographic order of their
+/// groups.
+/// 
+/// # Examples
+/// 
+/// ```
+/// use gen_combinations::MultisetCombinations;
+/// 
+/// let hand = ['A', 'K', 'A', 'Q'];
+/// for combo in MultisetCombinations::new(&hand, 2) {
+///     println!("{:?}", combo);
+///     // ['A', 'A']
+///     // ['A', 'K']
+///     // ['A', 'Q']
+///     // ['K', 'Q']
+/// }
+/// ```
+/// 
+/// [`CombinationIterator`]: struct.CombinationIterator.html
+#[derive(Debug)]
+pub struct MultisetCombinations<'a, T> {
+    groups: Vec<&'a T>, // one representative item per group
+    limits: Vec<usize>, // the number of items in each group
+    counts: Vec<usize>, // the number of each group in the next combination
+    remaining: Remaining,
+    buffer: Vec<&'a T>, // reused by next_slice
+}
+
+impl<'a, T: Ord> MultisetCombinations<'a, T> {
+    /// Creates an iterator over the distinct combinations of `items` with length `n`, treating equal items as
+    /// interchangeable.
+    /// 
+    /// If `n` is 0 or greater than `items.len()`, the iterator will produce no values.
+    pub fn new(items: &'a [T], n: usize) -> MultisetCombinations<'a, T> {
+        MultisetCombinations::new_by_key(items, n, |item| item)
+    }
+}
+
+impl<'a, T> MultisetCombinations<'a, T> {
+    /// Creates an iterator over the distinct combinations of `items` with length `n`, treating items with equal keys
+    /// as interchangeable.
+    /// 
+    /// Only the first item with each key is ever produced.
+    /// 
+    /// If `n` is 0 or greater than `items.len()`, the iterator will produce no values.
+    /// 
+    /// # Examples
+    /// 
+    /// ```
+    /// use gen_combinations::MultisetCombinations;
+    /// 
+    /// let words = ["apple", "avocado", "banana"];
+    /// let by_letter: Vec<_> = MultisetCombinations::new_by_key(&words, 2, |w| w.as_bytes()[0]).collect();
+    /// assert_eq!(by_letter, [[&"apple", &"apple"], [&"apple", &"banana"]]);
+    /// ```
+    pub fn new_by_key<K, F>(items: &'a [T], n: usize, key: F) -> MultisetCombinations<'a, T>
+    where
+        K: Ord,
+        F: FnMut(&'a T) -> K,
+    {
+        let (groups, limits) = group_by_key(items, key);
+        MultisetCombinations::with_groups(groups, limits, n)
+    }
+
+    /// Creates an iterator over the distinct combinations with length `n` of a multiset given as `(item, count)`
+    /// pairs, where each item may appear up to `count` times.
+    /// 
+    /// If `n` is 0 or greater than the sum of the counts, the iterator will produce no values.
+    /// 
+    /// # Examples
+    /// 
+    /// ```
+    /// use gen_combinations::MultisetCombinations;
+    /// 
+    /// let scoops = [("vanilla", 2), ("chocolate", 1)];
+    /// let cones: Vec<_> = MultisetCombinations::from_counts(&scoops, 2).collect();
+    /// assert_eq!(cones, [[&"vanilla", &"vanilla"], [&"vanilla", &"chocolate"]]);
+    /// ```
+    pub fn from_counts(pairs: &'a [(T, usize)], n: usize) -> MultisetCombinations<'a, T> {
+        let (groups, limits) = pairs.iter().filter(|(_, count)| *count > 0).map(|(item, count)| (item, *count)).unzip();
+        MultisetCombinations::with_groups(groups, limits, n)
+    }
+
+    fn with_groups(groups: Vec<&'a T>, limits: Vec<usize>, n: usize) -> MultisetCombinations<'a, T> {
+        let mut counts = vec![0; limits.len()];
+        let remaining = if n == 0 || !fill(&mut counts, &limits, n) {
+            Remaining::zero()
+        } else {
+            Remaining::new(wide_count_multiset(&limits, n))
+        };
+        MultisetCombinations { groups, limits, counts, remaining, buffer: Vec::new() }
+    }
+
+    /// Returns how many items from each group are in the next combination, with the groups in the order their first
+    /// items appear, or `None` if there are no combinations left.
+    pub fn counts(&self) -> Option<&[usize]> {
+        if self.remaining.is_zero() {
+            None
+        } else {
+            Some(&self.counts)
+        }
+    }
+
+    /// Returns the number of combinations left, or `None` if it does not fit in a `usize` and [`len`] would panic.
+    /// 
+    /// [`len`]: https://doc.rust-lang.org/std/iter/trait.ExactSizeIterator.html#method.len
+    pub fn remaining(&self) -> Option<usize> {
+        self.remaining.get()
+    }
+
+    /// Advances the iterator and returns the next combination, like [`next`], but in a buffer owned by the iterator
+    /// instead of a newly allocated `Vec`.
+    /// 
+    /// [`next`]: https://doc.rust-lang.org/std/iter/trait.Iterator.html#tymethod.next
+    pub fn next_slice(&mut self) -> Option<&[&'a T]> {
+        if self.remaining.is_zero() {
+            return None;
+        }
+        let mut buffer = std::mem::take(&mut self.buffer);
+        buffer.clear();
+        self.write(&mut buffer);
+        self.step();
+        self.buffer = buffer;
+        Some(&self.buffer)
+    }
+
+    fn write(&self, combo: &mut Vec<&'a T>) {
+        for (item, &count) in self.groups.iter().zip(&self.counts) {
+            combo.extend(std::iter::repeat_n(*item, count));
+        }
+    }
+
+    fn step(&mut self) {
+        self.remaining = self.remaining.consumed(1, || None);
+        if !successor(&mut self.counts, &self.limits) {
+            self.remaining = Remaining::zero();
+        }
+    }
+}
+
+impl<T> Clone for MultisetCombinations<'_, T> {
+    fn clone(&self) -> Self {
+        MultisetCombinations {
+            groups: self.groups.clone(),
+            limits: self.limits.clone(),
+            counts: self.counts.clone(),
+            remaining: self.remaining,
+            buffer: Vec::new(),
+        }
+    }
+}
+
+impl<'a, T> Iterator for MultisetCombinations<'a, T> {
+    type Item = Vec<&'a T>;
+
+    fn next(&mut self) -> Option<Vec<&'a T>> {
+        if self.remaining.is_zero() {
+            return None;
+        }
+        let mut ret = Vec::new();
+        self.write(&mut ret);
+        self.step();
+        Some(ret)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.remaining.size_hint()
+    }
+}
+
+impl<T> ExactSizeIterator for MultisetCombinations<'_, T> {}
+
+impl<T> FusedIterator for MultisetCombinations<'_, T> {}
+
+/// Returns the number of distinct combinations of `k` items from a multiset with the given number of copies of each
+/// item, or `None` if it does not fit in a `usize`.
+/// 
+/// # Examples
+/// 
+/// ```
+/// // two aces, a king and a queen
+/// assert_eq!(gen_combinations::count_multiset(&[2, 1, 1], 2), Some(4));
+/// ```
+pub fn count_multiset(counts: &[usize], k: usize) -> Option<usize> {
+    narrow(wide_count_multiset(counts, k)?)
+}
+
+/// Returns the number of distinct combinations of `k` items from a multiset, or `None` if it does not fit in a `u128`.
+fn wide_count_multiset(counts: &[usize], k: usize) -> Option<u128> {
+    // no group can give more than k items, and leaving out the rest is the same as taking k, so take whichever is fewer
+    let total: u128 = counts.iter().map(|&count| count.min(k) as u128).sum();
+    if k as u128 > total {
+        return Some(0);
+    }
+    let k = k.min((total - k as u128) as usize);
+    // ways[j] is the number of ways to pick j items from the groups so far
+    let mut ways = vec![Some(0u128); k + 1];
+    ways[0] = Some(1);
+    for &count in counts {
+        // the ways to pick j are those to pick from j - count to j before, a window that slides along one at a time
+        let mut window = Some(0u128);
+        ways = (0..=k)
+            .map(|j| {
+                if let Some(out) = j.checked_sub(count.saturating_add(1)) {
+                    window = window.zip(ways[out]).map(|(window, out)| window - out);
+                }
+                window = window.zip(ways[j]).and_then(|(window, new)| window.checked_add(new));
+                window
+            })
+            .collect();
+    }
+    ways[k]
+}
+
+/// Groups the items with equal keys, returning the first item of each group and the size of each group, in the order
+/// their first items appear.
+pub(crate) fn group_by_key<'a, T, K, F>(items: &'a [T], mut key: F) -> (Vec<&'a T>, Vec<usize>)
+where
+    K: Ord,
+    F: FnMut(&'a T) -> K,
+{
+    let mut keyed: Vec<(K, usize)> = items.iter().enumerate().map(|(i, item)| (key(item), i)).collect();
+    keyed.sort();
+    // (first index, size) of each group
+    let mut groups: Vec<(usize, usize)> = Vec::new();
+    for (j, (k, i)) in keyed.iter().enumerate() {
+        if j > 0 && keyed[j - 1].0 == *k {
+            groups.last_mut().unwrap().1 += 1;
+        } else {
+            groups.push((*i, 1));
+        }
+    }
+    groups.sort();
+    groups.into_iter().map(|(i, size)| (&items[i], size)).unzip()
+}
+
+/// Fills `counts` with the first combination of `k` items, taking as many as possible from the earliest groups.
+/// 
+/// Returns `false` if there are fewer than `k` items in total.
+fn fill(counts: &mut [usize], limits: &[usize], mut k: usize) -> bool {
+    for (count, &limit) in counts.iter_mut().zip(limits) {
+        *count = limit.min(k);
+        k -= *count;
+    }
+    k == 0
+}
+
+/// Moves `counts` to the next combination, which takes fewer items from some early group.
+/// 
+/// Returns `false`, leaving `counts` unchanged, if it is already the last combination.
+fn successor(counts: &mut [usize], limits: &[usize]) -> bool {
+    // take one fewer from the last group that can spare one to the groups after it, then refill those greedily
+    let mut taken = 0;
+    let mut room = 0;
+    for i in (0..counts.len()).rev() {
+        if counts[i] > 0 && room > taken {
+            counts[i] -= 1;
+            fill(&mut counts[i + 1..], &limits[i + 1..], taken + 1);
+            return true;
+        }
+        taken += counts[i];
+        room += limits[i];
+    }
+    false
+}
+
+#[test]
+fn each_value_combination_once() {
+    use crate::CombinationIterator;
+
+    let items = [3, 1, 3, 2, 1, 3];
+    let mut expected: Vec<Vec<i32>> = CombinationIterator::new(&items, 3)
+        .map(|combo| {
+            let mut values: Vec<i32> = combo.into_iter().copied().collect();
+            values.sort_by_key(|v| items.iter().position(|x| x == v));
+            values
+        })
+        .collect();
+    expected.sort_by_key(|values| values.iter().map(|v| items.iter().position(|x| x == v)).collect::<Vec<_>>());
+    expected.dedup();
+
+    let combos: Vec<Vec<i32>> =
+        MultisetCombinations::new(&items, 3).map(|combo| combo.into_iter().copied().collect()).collect();
+    assert_eq!(combos, expected);
+    assert_eq!(MultisetCombinations::new(&items, 3).len(), expected.len());
+    assert_eq!(count_multiset(&[3, 2, 1], 3), Some(expected.len()));
+}
+
+#[test]
+fn multiset_from_counts() {
+    let pairs = [('a', 2), ('b', 0), ('c', 3)];
+    let mut c = MultisetCombinations::from_counts(&pairs, 3);
+    assert_eq!(c.counts(), Some(&[2, 1][..]));
+    assert_eq!(c.next_slice(), Some(&[&'a', &'a', &'c'][..]));
+    assert_eq!(c.next(), Some(vec![&'a', &'c', &'c']));
+    assert_eq!(c.next(), Some(vec![&'c', &'c', &'c']));
+    assert_eq!(c.next(), None);
+    assert_eq!(c.counts(), None);
+
+    assert_eq!(MultisetCombinations::from_counts(&pairs, 6).count(), 0);
+    assert_eq!(MultisetCombinations::from_counts(&pairs, 0).count(), 0);
+    assert_eq!(MultisetCombinations::from_counts(&pairs, 5).count(), 1);
+
+    // more items than there are, or a sum of ways along the way too big for a usize
+    assert_eq!(count_multiset(&[2, 1, 1], usize::MAX), Some(0));
+    assert_eq!(count_multiset(&[usize::MAX, 1], usize::MAX), Some(2));
+    assert_eq!(count_multiset(&[1; 64], 64), Some(1));
+    assert_eq!(count_multiset(&[1; 64], 63), Some(64));
+    assert_eq!(count_multiset(&[2; 40], 40), Some(934_837_217_271_732_457));
+}
+
+#[test]
+fn multiset_by_key() {
+    let items = ["one", "two", "three", "four", "five"];
+    let by_len: Vec<_> = MultisetCombinations::new_by_key(&items, 4, |s| s.len()).collect();
+    assert_eq!(
+        by_len,
+        [
+            vec![&"one", &"one", &"three", &"four"],
+            vec![&"one", &"one", &"four", &"four"],
+            vec![&"one", &"three", &"four", &"four"],
+        ]
+    );
+}
@@ -0,0 +1,131 @@
+use crate::cursor::Cursor;
+use crate::Order;
+
+/// Iterates over all possible combinations of items, lending out mutable references to the items of each one.
+/// 
+/// This is not an [`Iterator`], since each combination borrows the iterator mutably and must be dropped before the
+/// next one is requested. The indices of a combination are always strictly increasing, which is what guarantees that
+/// its references are to distinct items; they are split off the slice with [`split_at_mut`], without any unsafe
+/// code. [`next_mut`] returns the references in a `Vec`, and [`next_array`] in an array, which avoids allocating.
+/// 
+/// # Examples
+/// 
+/// ```
+/// use gen_combinations::CombinationsMut;
+/// 
+/// // swapping every pair that is out of order sorts the items
+/// let mut items = [3, 1, 4, 1, 5, 9, 2, 6];
+/// let mut pairs = CombinationsMut::new(&mut items, 2);
+/// while let Some([a, b]) = pairs.next_array() {
+///     if a > b {
+///         std::mem::swap(a, b);
+///     }
+/// }
+/// assert_eq!(items, [1, 1, 2, 3, 4, 5, 6, 9]);
+/// ```
+/// 
+/// [`Iterator`]: https://doc.rust-lang.org/std/iter/trait.Iterator.html
+/// [`split_at_mut`]: https://doc.rust-lang.org/std/primitive.slice.html#method.split_at_mut
+/// [`next_mut`]: #method.next_mut
+/// [`next_array`]: #method.next_array
+#[derive(Debug)]
+pub struct CombinationsMut<'a, T> {
+    items: &'a mut [T],
+    cursor: Cursor,
+}
+
+impl<'a, T> CombinationsMut<'a, T> {
+    /// Creates a lending iterator over combinations of `items` with length `n`.
+    /// 
+    /// If `n` is 0 or greater than `items.len()`, it will produce no values.
+    pub fn new(items: &'a mut [T], n: usize) -> CombinationsMut<'a, T> {
+        let cursor = Cursor::new(items.len(), n);
+        CombinationsMut { items, cursor }
+    }
+
+    /// Creates a lending iterator over combinations of `items` with length `n` in the given order.
+    pub fn with_order(items: &'a mut [T], n: usize, order: Order) -> CombinationsMut<'a, T> {
+        let cursor = Cursor::starting_at(items.len(), (0..n).collect(), order);
+        CombinationsMut { items, cursor }
+    }
+
+    /// Returns the indices into `items` of the next combination, or an empty slice if there are no combinations left.
+    pub fn indices(&self) -> &[usize] {
+        self.cursor.front()
+    }
+
+    /// Returns the number of combinations left, or `None` if it does not fit in a `usize`.
+    pub fn remaining(&self) -> Option<usize> {
+        self.cursor.remaining()
+    }
+
+    /// Advances the iterator and returns mutable references to the items of the next combination.
+    pub fn next_mut(&mut self) -> Option<Vec<&mut T>> {
+        let items = &mut *self.items;
+        self.cursor.next_front(move |combo| {
+            let mut ret = Vec::with_capacity(combo.len());
+            split_disjoint(items, combo, |item| ret.push(item));
+            ret
+        })
+    }
+
+    /// Advances the iterator and returns mutable references to the items of the next combination in an array.
+    /// 
+    /// # Panics
+    /// 
+    /// Panics if `K` is not the length of the combinations.
+    pub fn next_array<const K: usize>(&mut self) -> Option<[&mut T; K]> {
+        let items = &mut *self.items;
+        self.cursor.next_front(move |combo| {
+            assert_eq!(combo.len(), K, "the combinations do not have length {}", K);
+            let mut ret: [Option<&mut T>; K] = std::array::from_fn(|_| None);
+            let mut slots = ret.iter_mut();
+            split_disjoint(items, combo, |item| *slots.next().unwrap() = Some(item));
+            ret.map(Option::unwrap)
+        })
+    }
+}
+
+/// Passes a mutable reference to each item of `items` at the strictly increasing `indices` to `f`.
+fn split_disjoint<'s, T>(items: &'s mut [T], indices: &[usize], mut f: impl FnMut(&'s mut T)) {
+    let mut rest = items;
+    let mut start = 0;
+    for &i in indices {
+        // each index is past the previous one, so it is always in what is left of the slice
+        let (item, tail) = std::mem::take(&mut rest)[i - start..].split_first_mut().unwrap();
+        f(item);
+        rest = tail;
+        start = i + 1;
+    }
+}
+
+#[test]
+fn combinations_mut() {
+    let mut items = [0; 4];
+    let mut c = CombinationsMut::new(&mut items, 2);
+    assert_eq!(c.remaining(), Some(6));
+    while let Some(mut combo) = c.next_mut() {
+        *combo[0] += 1;
+        *combo[1] += 10;
+    }
+    // item i is first in 3 - i pairs and second in i pairs
+    assert_eq!(items, [3, 12, 21, 30]);
+
+    let mut c = CombinationsMut::with_order(&mut items, 3, Order::Colexicographic);
+    assert_eq!(c.indices(), &[0, 1, 2]);
+    assert_eq!(c.next_array(), Some([&mut 3, &mut 12, &mut 21]));
+    assert_eq!(c.indices(), &[0, 1, 3]);
+    let [a, b, d] = c.next_array().unwrap();
+    std::mem::swap(a, d);
+    *b = 0;
+    assert_eq!(c.remaining(), Some(2));
+    assert_eq!(items, [30, 0, 21, 3]);
+    assert!(CombinationsMut::new(&mut items, 5).next_mut().is_none());
+}
+
+#[test]
+#[should_panic]
+fn combinations_mut_wrong_length() {
+    let mut items = [1, 2, 3];
+    CombinationsMut::new(&mut items, 2).next_array::<3>();
+}
@@ -0,0 +1,97 @@
+/// A natural number of any size, as base 2^64 digits from the least significant, for counts summed from terms that
+/// may not fit in a `u128` although the counts do.
+#[derive(Clone, Debug)]
+pub(crate) struct Natural(Vec<u64>);
+
+impl Natural {
+    pub(crate) fn new(x: u128) -> Natural {
+        let mut ret = Natural(vec![x as u64, (x >> 64) as u64]);
+        ret.trim();
+        ret
+    }
+
+    /// Returns the binomial coefficient `C(n, k)`.
+    pub(crate) fn binomial(n: u128, k: u128) -> Natural {
+        if k > n {
+            return Natural::new(0);
+        }
+        let mut ret = Natural::new(1);
+        for i in 0..k.min(n - k) {
+            // C(n, i + 1) = C(n, i) * (n - i) / (i + 1)
+            ret = ret.times(&Natural::new(n - i));
+            ret.divide(i as u64 + 1);
+        }
+        ret
+    }
+
+    fn trim(&mut self) {
+        while self.0.last() == Some(&0) {
+            self.0.pop();
+        }
+    }
+
+    pub(crate) fn times(&self, other: &Natural) -> Natural {
+        let mut digits = vec![0u64; self.0.len() + other.0.len()];
+        for (i, &a) in self.0.iter().enumerate() {
+            let mut carry = 0u128;
+            for (j, &b) in other.0.iter().enumerate() {
+                let t = a as u128 * b as u128 + digits[i + j] as u128 + carry;
+                digits[i + j] = t as u64;
+                carry = t >> 64;
+            }
+            digits[i + other.0.len()] = carry as u64;
+        }
+        let mut ret = Natural(digits);
+        ret.trim();
+        ret
+    }
+
+    /// Divides by `d`, which must divide exactly.
+    pub(crate) fn divide(&mut self, d: u64) {
+        let mut rest = 0u128;
+        for digit in self.0.iter_mut().rev() {
+            let t = rest << 64 | *digit as u128;
+            *digit = (t / d as u128) as u64;
+            rest = t % d as u128;
+        }
+        self.trim();
+    }
+
+    pub(crate) fn add(&mut self, other: &Natural) {
+        if self.0.len() < other.0.len() {
+            self.0.resize(other.0.len(), 0);
+        }
+        let mut carry = false;
+        for (i, digit) in self.0.iter_mut().enumerate() {
+            let (sum, over) = digit.overflowing_add(other.0.get(i).copied().unwrap_or(0));
+            let (sum, again) = sum.overflowing_add(carry as u64);
+            *digit = sum;
+            carry = over || again;
+        }
+        if carry {
+            self.0.push(1);
+        }
+    }
+
+    /// Subtracts `other`, which must be no bigger.
+    pub(crate) fn subtract(&mut self, other: &Natural) {
+        let mut borrow = false;
+        for (i, digit) in self.0.iter_mut().enumerate() {
+            let (difference, under) = digit.overflowing_sub(other.0.get(i).copied().unwrap_or(0));
+            let (difference, again) = difference.overflowing_sub(borrow as u64);
+            *digit = difference;
+            borrow = under || again;
+        }
+        self.trim();
+    }
+
+    /// Returns the number as a `u128`, or `None` if it does not fit.
+    pub(crate) fn narrow(&self) -> Option<u128> {
+        match self.0[..] {
+            [] => Some(0),
+            [a] => Some(a as u128),
+            [a, b] => Some(a as u128 | (b as u128) << 64),
+            _ => None,
+        }
+    }
+}
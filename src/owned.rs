@@ -0,0 +1,197 @@
+use crate::cursor::Cursor;
+use crate::{unrank, Order};
+use std::iter::FusedIterator;
+use std::ops::Deref;
+
+/// Iterates over all possible combinations of items that it owns, producing clones of the items.
+/// 
+/// This is like [`CombinationIterator`], but it takes ownership of the items, which can be in a `Vec<T>`, a
+/// `Box<[T]>`, an `Arc<[T]>` or anything else that dereferences to a slice. It can therefore be returned from
+/// functions, stored in structs, or sent to other threads, and is `'static` and `Send` whenever the items are. The
+/// combinations are `Vec<T>`s of clones, which for `Copy` items are just the values themselves.
+/// 
+/// # Examples
+/// 
+/// ```
+/// use gen_combinations::OwnedCombinations;
+/// use std::sync::Arc;
+/// 
+/// fn pairs(items: Arc<[u32]>) -> OwnedCombinations<u32, Arc<[u32]>> {
+///     OwnedCombinations::new(items, 2)
+/// }
+/// 
+/// let c = pairs(Arc::from(vec![1, 2, 3]));
+/// let sums: Vec<u32> = std::thread::spawn(move || c.map(|pair| pair[0] + pair[1]).collect()).join().unwrap();
+/// assert_eq!(sums, [3, 4, 5]);
+/// ```
+/// 
+/// [`CombinationIterator`]: struct.CombinationIterator.html
+#[derive(Debug)]
+pub struct OwnedCombinations<T, S = Vec<T>> {
+    items: S,
+    cursor: Cursor,
+    buffer: Vec<T>, // reused by next_slice
+}
+
+impl<T: Clone, S: Deref<Target = [T]>> OwnedCombinations<T, S> {
+    /// Creates an iterator over combinations of `items` with length `n`, taking ownership of `items`.
+    /// 
+    /// If `n` is 0 or greater than `items.len()`, the iterator will produce no values.
+    pub fn new(items: S, n: usize) -> OwnedCombinations<T, S> {
+        let cursor = Cursor::new(items.len(), n);
+        OwnedCombinations { items, cursor, buffer: Vec::new() }
+    }
+
+    /// Creates an iterator over combinations of `items` with length `n`, starting at the combination with the given
+    /// lexicographic `rank` (see [`rank`]).
+    /// 
+    /// If `rank` is past the last combination, the iterator will produce no values.
+    /// 
+    /// [`rank`]: fn.rank.html
+    pub fn from_rank(items: S, n: usize, rank: usize) -> OwnedCombinations<T, S> {
+        let front = if n == 0 { Vec::new() } else { unrank(items.len(), n, rank).unwrap_or_default() };
+        let cursor = Cursor::starting_at(items.len(), front, Order::Lexicographic);
+        OwnedCombinations { items, cursor, buffer: Vec::new() }
+    }
+
+    /// Creates an iterator over combinations of `items` with length `n` in the given order.
+    pub fn with_order(items: S, n: usize, order: Order) -> OwnedCombinations<T, S> {
+        let cursor = Cursor::starting_at(items.len(), (0..n).collect(), order);
+        OwnedCombinations { items, cursor, buffer: Vec::new() }
+    }
+
+    /// Returns the order in which the combinations are produced.
+    pub fn order(&self) -> Order {
+        self.cursor.order()
+    }
+
+    /// Returns the items that combinations are taken from.
+    pub fn items(&self) -> &[T] {
+        &self.items
+    }
+
+    /// Returns the items, consuming the iterator.
+    pub fn into_inner(self) -> S {
+        self.items
+    }
+
+    /// Returns the number of combinations left, or `None` if it does not fit in a `usize` and [`len`] would panic.
+    /// 
+    /// [`len`]: https://doc.rust-lang.org/std/iter/trait.ExactSizeIterator.html#method.len
+    pub fn remaining(&self) -> Option<usize> {
+        self.cursor.remaining()
+    }
+
+    /// Returns the indices into the items of the next combination from the front, or an empty slice if there are no
+    /// combinations left.
+    pub fn indices(&self) -> &[usize] {
+        self.cursor.front()
+    }
+
+    /// Advances the iterator and returns the next combination, like [`next`], but in a buffer owned by the iterator
+    /// instead of a newly allocated `Vec`.
+    /// 
+    /// [`next`]: https://doc.rust-lang.org/std/iter/trait.Iterator.html#tymethod.next
+    pub fn next_slice(&mut self) -> Option<&[T]> {
+        let mut buffer = std::mem::take(&mut self.buffer);
+        let more = self.next_into(&mut buffer);
+        self.buffer = buffer;
+        if more {
+            Some(&self.buffer)
+        } else {
+            None
+        }
+    }
+
+    /// Advances the iterator and writes clones of the next combination into `combo`, replacing its contents.
+    /// 
+    /// Returns `false`, leaving `combo` unchanged, if there are no combinations left.
+    pub fn next_into(&mut self, combo: &mut Vec<T>) -> bool {
+        let items = &*self.items;
+        self.cursor
+            .next_front(|indices| {
+                combo.clear();
+                combo.extend(indices.iter().map(|i| items[*i].clone()));
+            })
+            .is_some()
+    }
+}
+
+impl<T, S: Clone> Clone for OwnedCombinations<T, S> {
+    fn clone(&self) -> Self {
+        OwnedCombinations { items: self.items.clone(), cursor: self.cursor.clone(), buffer: Vec::new() }
+    }
+}
+
+impl<T: Clone, S: Deref<Target = [T]>> Iterator for OwnedCombinations<T, S> {
+    type Item = Vec<T>;
+
+    fn next(&mut self) -> Option<Vec<T>> {
+        let items = &*self.items;
+        self.cursor.next_front(|combo| gather(items, combo))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.cursor.size_hint()
+    }
+
+    fn count(self) -> usize {
+        self.cursor.len()
+    }
+
+    fn nth(&mut self, n: usize) -> Option<Vec<T>> {
+        let items = &*self.items;
+        self.cursor.nth_front(n, |combo| gather(items, combo))
+    }
+}
+
+impl<T: Clone, S: Deref<Target = [T]>> DoubleEndedIterator for OwnedCombinations<T, S> {
+    fn next_back(&mut self) -> Option<Vec<T>> {
+        let items = &*self.items;
+        self.cursor.next_back(|combo| gather(items, combo))
+    }
+
+    fn nth_back(&mut self, n: usize) -> Option<Vec<T>> {
+        let items = &*self.items;
+        self.cursor.nth_back(n, |combo| gather(items, combo))
+    }
+}
+
+impl<T: Clone, S: Deref<Target = [T]>> ExactSizeIterator for OwnedCombinations<T, S> {}
+
+impl<T: Clone, S: Deref<Target = [T]>> FusedIterator for OwnedCombinations<T, S> {}
+
+/// Returns clones of the items at the given indices.
+fn gather<T: Clone>(items: &[T], indices: &[usize]) -> Vec<T> {
+    indices.iter().map(|i| items[*i].clone()).collect()
+}
+
+#[test]
+fn owned_matches_borrowed() {
+    use crate::CombinationIterator;
+
+    let items = vec![String::from("a"), String::from("b"), String::from("c"), String::from("d")];
+    let expected: Vec<Vec<String>> =
+        CombinationIterator::new(&items, 2).map(|combo| combo.into_iter().cloned().collect()).collect();
+    assert_eq!(OwnedCombinations::new(items.clone(), 2).collect::<Vec<_>>(), expected);
+    assert_eq!(OwnedCombinations::new(items.clone().into_boxed_slice(), 2).rev().count(), 6);
+    assert_eq!(OwnedCombinations::from_rank(items.clone(), 2, 4).next().as_ref(), Some(&expected[4]));
+
+    let mut c = OwnedCombinations::with_order(items, 2, Order::Colexicographic);
+    assert_eq!(c.next_slice(), Some(&expected[0][..]));
+    assert_eq!(c.indices(), &[0, 2]);
+    assert_eq!(c.nth_back(1), Some(vec![String::from("b"), String::from("d")]));
+    assert_eq!(c.len(), 3);
+    assert_eq!(c.into_inner().len(), 4);
+}
+
+#[test]
+fn owned_is_static_and_send() {
+    fn assert_static_send<I: Iterator + Send + 'static>(iter: I) -> I {
+        iter
+    }
+
+    let c = assert_static_send(OwnedCombinations::new(std::sync::Arc::<[i32]>::from(vec![1, 2, 3]), 2));
+    let combos: Vec<Vec<i32>> = std::thread::spawn(move || c.collect()).join().unwrap();
+    assert_eq!(combos, [[1, 2], [1, 3], [2, 3]]);
+}
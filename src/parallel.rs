@@ -0,0 +1,135 @@
+use crate::CombinationIterator;
+use rayon::iter::plumbing::{bridge, Consumer, Producer, ProducerCallback, UnindexedConsumer};
+use rayon::iter::{IndexedParallelIterator, IntoParallelIterator, ParallelIterator};
+
+/// A parallel iterator over combinations, created by calling [`into_par_iter`] on a [`CombinationIterator`].
+/// 
+/// The combinations are split between threads by rank, jumping to the start of each range by unranking, so each piece
+/// of work is exactly the requested size and [`collect`] keeps the combinations in the same order as the sequential
+/// iterator. It is only available with the `rayon` feature.
+/// 
+/// # Examples
+/// 
+/// ```
+/// use gen_combinations::CombinationIterator;
+/// use rayon::prelude::*;
+/// 
+/// let items: Vec<u32> = (1..=20).collect();
+/// let combos = CombinationIterator::new(&items, 4);
+/// let total: u32 = combos.clone().into_par_iter().map(|combo| combo.into_iter().sum::<u32>()).sum();
+/// assert_eq!(total, combos.map(|combo| combo.into_iter().sum::<u32>()).sum());
+/// 
+/// let collected: Vec<Vec<&u32>> = CombinationIterator::new(&items, 4).into_par_iter().collect();
+/// assert_eq!(collected, CombinationIterator::new(&items, 4).collect::<Vec<_>>());
+/// ```
+/// 
+/// [`into_par_iter`]: https://docs.rs/rayon/1/rayon/iter/trait.IntoParallelIterator.html#tymethod.into_par_iter
+/// [`CombinationIterator`]: struct.CombinationIterator.html
+/// [`collect`]: https://docs.rs/rayon/1/rayon/iter/trait.ParallelIterator.html#method.collect
+#[derive(Clone, Debug)]
+pub struct ParCombinations<'a, T> {
+    combos: CombinationIterator<'a, T>,
+}
+
+impl<'a, T: Sync> IntoParallelIterator for CombinationIterator<'a, T> {
+    type Iter = ParCombinations<'a, T>;
+    type Item = Vec<&'a T>;
+
+    /// Converts the combinations left in the iterator into a parallel iterator over them, in the same order.
+    /// 
+    /// # Panics
+    /// 
+    /// Panics if there are more than `usize::MAX` combinations left.
+    fn into_par_iter(self) -> ParCombinations<'a, T> {
+        assert!(self.cursor.remaining().is_some(), "more than usize::MAX combinations");
+        ParCombinations { combos: self }
+    }
+}
+
+impl<'a, T: Sync> ParallelIterator for ParCombinations<'a, T> {
+    type Item = Vec<&'a T>;
+
+    fn drive_unindexed<C: UnindexedConsumer<Vec<&'a T>>>(self, consumer: C) -> C::Result {
+        bridge(self, consumer)
+    }
+
+    fn opt_len(&self) -> Option<usize> {
+        Some(self.len())
+    }
+}
+
+impl<'a, T: Sync> IndexedParallelIterator for ParCombinations<'a, T> {
+    fn len(&self) -> usize {
+        self.combos.len()
+    }
+
+    fn drive<C: Consumer<Vec<&'a T>>>(self, consumer: C) -> C::Result {
+        bridge(self, consumer)
+    }
+
+    fn with_producer<CB: ProducerCallback<Vec<&'a T>>>(self, callback: CB) -> CB::Output {
+        callback.callback(CombinationProducer { combos: self.combos })
+    }
+}
+
+/// Produces the combinations of a range of ranks, splitting it by moving either end of a copy of the range.
+struct CombinationProducer<'a, T> {
+    combos: CombinationIterator<'a, T>,
+}
+
+impl<'a, T: Sync> Producer for CombinationProducer<'a, T> {
+    type Item = Vec<&'a T>;
+    type IntoIter = CombinationIterator<'a, T>;
+
+    fn into_iter(self) -> CombinationIterator<'a, T> {
+        self.combos
+    }
+
+    fn split_at(self, index: usize) -> (Self, Self) {
+        let len = self.combos.len();
+        let mut left = self.combos.clone();
+        let mut right = self.combos;
+        left.cursor.skip_back(len - index);
+        right.cursor.skip_front(index);
+        (CombinationProducer { combos: left }, CombinationProducer { combos: right })
+    }
+}
+
+#[test]
+fn parallel_matches_sequential() {
+    use crate::Order;
+
+    let items: Vec<usize> = (0..12).collect();
+    for &order in &[Order::Lexicographic, Order::Colexicographic] {
+        for k in 0..=13 {
+            let expected: Vec<Vec<&usize>> = CombinationIterator::with_order(&items, k, order).collect();
+            let par = CombinationIterator::with_order(&items, k, order).into_par_iter();
+            assert_eq!(par.len(), expected.len());
+            let found: Vec<Vec<&usize>> = par.with_min_len(1).with_max_len(3).collect();
+            assert_eq!(found, expected);
+            let rev: Vec<Vec<&usize>> =
+                CombinationIterator::with_order(&items, k, order).into_par_iter().rev().collect();
+            assert!(rev.iter().eq(expected.iter().rev()));
+        }
+    }
+
+    // only the combinations left in the iterator are split
+    let mut combos = CombinationIterator::new(&items, 5);
+    combos.nth(100);
+    combos.next_back();
+    let expected: Vec<Vec<&usize>> = combos.clone().collect();
+    let found: Vec<Vec<&usize>> = combos.into_par_iter().collect();
+    assert_eq!(found, expected);
+}
+
+#[test]
+fn parallel_skip_and_take() {
+    let items: Vec<usize> = (0..60).collect();
+    let par = CombinationIterator::new(&items, 6).into_par_iter();
+    assert_eq!(par.len(), 50_063_860);
+    let found: Vec<Vec<&usize>> = par.skip(40_000_000).take(5).collect();
+    let expected: Vec<Vec<&usize>> = CombinationIterator::from_rank(&items, 6, 40_000_000).take(5).collect();
+    assert_eq!(found, expected);
+    let last: Vec<Vec<&usize>> = CombinationIterator::new(&items, 6).into_par_iter().rev().take(1).collect();
+    assert_eq!(last, [[&54, &55, &56, &57, &58, &59]]);
+}
@@ -0,0 +1,890 @@
+use crate::grouped::{grouped_successor, grouped_unrank_into, wide_count, wide_grouped_rank};
+use crate::narrow;
+use crate::remaining::{Ranked, Remaining};
+use std::iter::FusedIterator;
+
+/// Iterates over all possible ways to partition a set of items into blocks, where neither the order of the blocks nor
+/// the order within each one matters.
+/// 
+/// Each partition is a `Vec` of blocks, each a `Vec` of the items in it. The items of a block are in the same order as
+/// in `items`, and the blocks are in order of their first items. Partitions are produced in lexicographic order of
+/// their restricted growth strings, which give the block of each item in turn (see [`indices`]).
+/// 
+/// There are [`bell`]`(items.len())` partitions in all, or [`stirling2`]`(items.len(), m)` into exactly `m` blocks. If
+/// `items` is empty, the iterator will produce no values.
+/// 
+/// # Examples
+/// 
+/// ```
+/// use gen_combinations::SetPartitions;
+/// 
+/// let items = [1, 2, 3];
+/// for partition in SetPartitions::new(&items) {
+///     println!("{:?}", partition);
+///     // [[1, 2, 3]]
+///     // [[1, 2], [3]]
+///     // [[1, 3], [2]]
+///     // [[1], [2, 3]]
+///     // [[1], [2], [3]]
+/// }
+/// ```
+/// 
+/// [`indices`]: #method.indices
+/// [`bell`]: fn.bell.html
+/// [`stirling2`]: fn.stirling2.html
+#[derive(Debug)]
+pub struct SetPartitions<'a, T> {
+    items: &'a [T],
+    max_blocks: usize,
+    exact: bool, // whether every partition has exactly max_blocks blocks
+    front: Vec<usize>, // the restricted growth string of the next partition
+    blocks: Vec<usize>, // the number of blocks among the first i + 1 items of the next partition
+    remaining: Remaining,
+    buffer: Vec<Vec<&'a T>>, // reused by next_slice
+}
+
+impl<'a, T> SetPartitions<'a, T> {
+    /// Creates an iterator over all partitions of `items`.
+    pub fn new(items: &[T]) -> SetPartitions<'_, T> {
+        SetPartitions::with_limit(items, items.len(), false)
+    }
+
+    /// Creates an iterator over the partitions of `items` into exactly `m` blocks.
+    /// 
+    /// If `m` is 0 or greater than `items.len()`, the iterator will produce no values.
+    /// 
+    /// # Examples
+    /// 
+    /// ```
+    /// use gen_combinations::SetPartitions;
+    /// 
+    /// let items = ["a", "b", "c", "d"];
+    /// let halves: Vec<_> = SetPartitions::with_blocks(&items, 2).collect();
+    /// assert_eq!(halves.len(), 7);
+    /// assert_eq!(halves[0], [vec![&"a", &"b", &"c"], vec![&"d"]]);
+    /// ```
+    pub fn with_blocks(items: &[T], m: usize) -> SetPartitions<'_, T> {
+        SetPartitions::with_limit(items, if m > items.len() { 0 } else { m }, true)
+    }
+
+    /// Creates an iterator over the partitions of `items` into at most `m` blocks.
+    /// 
+    /// If `m` is 0, the iterator will produce no values.
+    pub fn with_max_blocks(items: &[T], m: usize) -> SetPartitions<'_, T> {
+        SetPartitions::with_limit(items, m.min(items.len()), false)
+    }
+
+    fn with_limit(items: &[T], max_blocks: usize, exact: bool) -> SetPartitions<'_, T> {
+        let n = items.len();
+        let remaining =
+            if max_blocks == 0 { Remaining::zero() } else { Remaining::new(completions(n, max_blocks, exact)[1][1]) };
+        let mut ret = SetPartitions {
+            items,
+            max_blocks,
+            exact,
+            front: Vec::new(),
+            blocks: Vec::new(),
+            remaining,
+            buffer: Vec::new(),
+        };
+        if !remaining.is_zero() {
+            ret.front = vec![0; n];
+            ret.blocks = vec![1; n];
+            fill(&mut ret.front, &mut ret.blocks, 1, max_blocks, exact);
+        }
+        ret
+    }
+
+    /// Returns the number of partitions left, or `None` if it does not fit in a `usize` and [`len`] would panic.
+    /// 
+    /// [`len`]: https://doc.rust-lang.org/std/iter/trait.ExactSizeIterator.html#method.len
+    pub fn remaining(&self) -> Option<usize> {
+        self.remaining.get()
+    }
+
+    /// Returns the restricted growth string of the next partition, or an empty slice if there are none left.
+    /// 
+    /// This gives the index of the block that each item is in, where blocks are numbered in order of their first items,
+    /// so it starts with 0 and each index is at most one more than all those before it.
+    pub fn indices(&self) -> &[usize] {
+        &self.front
+    }
+
+    /// Advances the iterator and returns the next partition, like [`next`], but in a buffer owned by the iterator
+    /// instead of a newly allocated `Vec`.
+    /// 
+    /// [`next`]: https://doc.rust-lang.org/std/iter/trait.Iterator.html#tymethod.next
+    pub fn next_slice(&mut self) -> Option<&[Vec<&'a T>]> {
+        let mut buffer = std::mem::take(&mut self.buffer);
+        let more = self.next_into(&mut buffer);
+        self.buffer = buffer;
+        if more {
+            Some(&self.buffer)
+        } else {
+            None
+        }
+    }
+
+    /// Advances the iterator and writes the next partition into `partition`, replacing its contents but reusing the
+    /// `Vec`s of its blocks.
+    /// 
+    /// Returns `false`, leaving `partition` unchanged, if there are no partitions left.
+    pub fn next_into(&mut self, partition: &mut Vec<Vec<&'a T>>) -> bool {
+        if self.remaining.is_zero() {
+            return false;
+        }
+        partition.resize_with(self.blocks[self.blocks.len() - 1], Vec::new);
+        for block in partition.iter_mut() {
+            block.clear();
+        }
+        for (item, &b) in self.items.iter().zip(&self.front) {
+            partition[b].push(item);
+        }
+        self.step();
+        true
+    }
+
+    fn count_remaining(&self) -> Option<u128> {
+        let table = completions(self.items.len(), self.max_blocks, self.exact);
+        Some(table[1][1]? - wide_partition_rank(&table, &self.front, &self.blocks)?)
+    }
+}
+
+impl<T> Ranked for SetPartitions<'_, T> {
+    fn left(&self) -> Remaining {
+        self.remaining
+    }
+
+    fn rank(&self) -> Option<u128> {
+        let table = completions(self.items.len(), self.max_blocks, self.exact);
+        wide_partition_rank(&table, &self.front, &self.blocks)
+    }
+
+    fn jump(&mut self, rank: u128, by: usize) {
+        let table = completions(self.items.len(), self.max_blocks, self.exact);
+        partition_unrank_into(&table, &mut self.front, &mut self.blocks, rank);
+        self.remaining = self.remaining.consumed(by, || Some(table[1][1]? - rank));
+    }
+
+    fn step(&mut self) {
+        if self.remaining.is_one() {
+            self.exhaust();
+            return;
+        }
+        successor(&mut self.front, &mut self.blocks, self.max_blocks, self.exact);
+        self.remaining = self.remaining.consumed(1, || self.count_remaining());
+    }
+
+    fn exhaust(&mut self) {
+        self.front.clear();
+        self.blocks.clear();
+        self.remaining = Remaining::zero();
+    }
+}
+
+impl<T> Clone for SetPartitions<'_, T> {
+    fn clone(&self) -> Self {
+        SetPartitions {
+            items: self.items,
+            max_blocks: self.max_blocks,
+            exact: self.exact,
+            front: self.front.clone(),
+            blocks: self.blocks.clone(),
+            remaining: self.remaining,
+            buffer: Vec::new(),
+        }
+    }
+}
+
+impl<'a, T> Iterator for SetPartitions<'a, T> {
+    type Item = Vec<Vec<&'a T>>;
+
+    fn next(&mut self) -> Option<Vec<Vec<&'a T>>> {
+        let mut ret = Vec::new();
+        if self.next_into(&mut ret) {
+            Some(ret)
+        } else {
+            None
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.remaining.size_hint()
+    }
+
+    fn count(self) -> usize {
+        self.remaining.get().expect("more than usize::MAX partitions")
+    }
+
+    fn nth(&mut self, n: usize) -> Option<Vec<Vec<&'a T>>> {
+        if self.skip_ahead(n) {
+            self.next()
+        } else {
+            None
+        }
+    }
+}
+
+impl<T> ExactSizeIterator for SetPartitions<'_, T> {}
+
+impl<T> FusedIterator for SetPartitions<'_, T> {}
+
+/// Returns the Bell number `B(n)`, the number of partitions of `n` items, or `None` if it does not fit in a `usize`.
+/// 
+/// Note that `B(0)` is 1, whereas a [`SetPartitions`] of no items produces no values.
+/// 
+/// # Examples
+/// 
+/// ```
+/// assert_eq!(gen_combinations::bell(4), Some(15));
+/// assert_eq!(gen_combinations::bell(100), None);
+/// ```
+/// 
+/// [`SetPartitions`]: struct.SetPartitions.html
+pub fn bell(n: usize) -> Option<usize> {
+    if n == 0 {
+        return Some(1);
+    }
+    narrow(completions(n, n, false)[1][1]?)
+}
+
+/// Returns the Stirling number of the second kind `S(n, k)`, the number of partitions of `n` items into exactly `k`
+/// blocks, or `None` if it does not fit in a `usize`.
+/// 
+/// Note that `S(0, 0)` is 1, whereas a [`SetPartitions`] of no items produces no values.
+/// 
+/// # Examples
+/// 
+/// ```
+/// assert_eq!(gen_combinations::stirling2(4, 2), Some(7));
+/// assert_eq!(gen_combinations::stirling2(4, 5), Some(0));
+/// ```
+/// 
+/// [`SetPartitions`]: struct.SetPartitions.html
+pub fn stirling2(n: usize, k: usize) -> Option<usize> {
+    if n == 0 || k == 0 || k > n {
+        return Some((n == k) as usize);
+    }
+    narrow(completions(n, k, true)[1][1]?)
+}
+
+/// Returns a table whose entry `[i][j]` is the number of ways to assign items `i..n` to blocks once `j` blocks have
+/// been used by the items before them, so that at most `m` blocks, or exactly `m` if `exact`, are used in all.
+/// 
+/// Entries that do not fit in a `u128` are `None`. `m` must be at least 1 and at most `n`.
+fn completions(n: usize, m: usize, exact: bool) -> Vec<Vec<Option<u128>>> {
+    let mut table = vec![vec![Some(0); m + 1]; n + 1];
+    for (j, count) in table[n].iter_mut().enumerate().skip(1) {
+        *count = Some((!exact || j == m) as u128);
+    }
+    for i in (1..n).rev() {
+        for j in 1..=m.min(i) {
+            // the item joins one of the j blocks so far, or starts a new one
+            let join = table[i + 1][j].and_then(|count| count.checked_mul(j as u128));
+            let start = if j < m { table[i + 1][j + 1] } else { Some(0) };
+            table[i][j] = join.and_then(|join| join.checked_add(start?));
+        }
+    }
+    table
+}
+
+/// Returns the rank of the restricted growth string `rgs`, where `blocks` holds its running number of blocks, or `None`
+/// if it does not fit in a `u128`.
+fn wide_partition_rank(table: &[Vec<Option<u128>>], rgs: &[usize], blocks: &[usize]) -> Option<u128> {
+    let mut rank = 0u128;
+    for i in 1..rgs.len() {
+        // every string that agrees before i but puts item i in an earlier block comes first
+        if rgs[i] > 0 {
+            rank = rank.checked_add(table[i + 1][blocks[i - 1]]?.checked_mul(rgs[i] as u128)?)?;
+        }
+    }
+    Some(rank)
+}
+
+/// Fills `rgs` and `blocks` with the restricted growth string with the given rank, which must be less than the number
+/// of them.
+fn partition_unrank_into(table: &[Vec<Option<u128>>], rgs: &mut [usize], blocks: &mut [usize], mut rank: u128) {
+    rgs[0] = 0;
+    blocks[0] = 1;
+    for i in 1..rgs.len() {
+        let j = blocks[i - 1];
+        // if there are too many ways to put item i in each old block, the first one alone is more than any rank
+        let b = match table[i + 1][j] {
+            Some(0) => j,
+            Some(each) => (rank / each).min(j as u128) as usize,
+            None => 0,
+        };
+        if b > 0 {
+            rank -= table[i + 1][j].unwrap() * b as u128;
+        }
+        rgs[i] = b;
+        blocks[i] = j.max(b + 1);
+    }
+}
+
+/// Moves the restricted growth string in `rgs` to the next one in lexicographic order with at most `m` blocks, or
+/// exactly `m` if `exact`, keeping `blocks` up to date.
+/// 
+/// Returns `false`, leaving `rgs` unchanged, if it is already the last one.
+fn successor(rgs: &mut [usize], blocks: &mut [usize], m: usize, exact: bool) -> bool {
+    let n = rgs.len();
+    for i in (1..n).rev() {
+        let b = rgs[i] + 1;
+        let used = blocks[i - 1].max(b + 1);
+        // item i can move to the next block if it exists or is the first new one, and the rest can still fill m
+        if b <= blocks[i - 1] && b < m && !(exact && used + (n - 1 - i) < m) {
+            rgs[i] = b;
+            blocks[i] = used;
+            fill(&mut rgs[i + 1..], &mut blocks[i + 1..], used, m, exact);
+            return true;
+        }
+    }
+    false
+}
+
+/// Fills `rgs` with the first assignment of its items to blocks after `used` blocks, keeping `blocks` up to date.
+fn fill(rgs: &mut [usize], blocks: &mut [usize], used: usize, m: usize, exact: bool) {
+    // everything goes in the first block, except that with exactly m blocks the last few items start the missing ones
+    let zeros = rgs.len() - if exact { m - used } else { 0 };
+    for (t, (slot, count)) in rgs.iter_mut().zip(blocks).enumerate() {
+        if t < zeros {
+            *slot = 0;
+            *count = used;
+        } else {
+            *slot = used + t - zeros;
+            *count = *slot + 1;
+        }
+    }
+}
+
+/// Iterates over all possible ways to partition a set of items into blocks of prescribed sizes, where the order within
+/// each block does not matter and neither does the order of blocks of the same size.
+/// 
+/// This is how to split 12 people into 4 teams of 3, or, with [`matchings`], to pair them up. Each partition is
+/// produced once, as a `Vec` with a block for each of `sizes`, holding its items in the same order as in `items`.
+/// Blocks of the same size are in order of their first items. There are [`count_sized_partitions`]`(sizes)` partitions.
+/// 
+/// If `sizes` is empty, any size is 0, or the sizes do not add up to `items.len()`, the iterator will produce no
+/// values.
+/// 
+/// # Examples
+/// 
+/// ```
+/// use gen_combinations::SizedPartitions;
+/// 
+/// let people = ["Ann", "Bob", "Cat", "Dan", "Eve"];
+/// let splits: Vec<_> = SizedPartitions::new(&people, &[2, 3]).collect();
+/// assert_eq!(splits.len(), 10);
+/// assert_eq!(splits[0], [vec![&"Ann", &"Bob"], vec![&"Cat", &"Dan", &"Eve"]]);
+/// 
+/// let teams = SizedPartitions::new(&[0; 12], &[3, 3, 3, 3]);
+/// assert_eq!(teams.len(), 15400);
+/// ```
+/// 
+/// [`matchings`]: #method.matchings
+/// [`count_sized_partitions`]: fn.count_sized_partitions.html
+#[derive(Debug)]
+pub struct SizedPartitions<'a, T> {
+    items: &'a [T],
+    runs: Runs,
+    steps: Vec<(usize, usize)>, // the (n, k) combinations that choose a partition, as for GroupedCombinations
+    choices: Vec<usize>, // the combinations that choose the next partition
+    front: Vec<usize>, // the block of each item in the next partition
+    remaining: Remaining,
+    buffer: Vec<Vec<&'a T>>, // reused by next_slice
+}
+
+impl<'a, T> SizedPartitions<'a, T> {
+    /// Creates an iterator over the partitions of `items` into blocks with the given `sizes`.
+    pub fn new(items: &'a [T], sizes: &[usize]) -> SizedPartitions<'a, T> {
+        let (runs, steps) = if valid(sizes) && sizes.iter().sum::<usize>() == items.len() {
+            plan(sizes)
+        } else {
+            (Vec::new(), Vec::new())
+        };
+        let remaining = if steps.is_empty() { Remaining::zero() } else { Remaining::new(wide_count(&steps)) };
+        let (choices, front, buffer) = (Vec::new(), Vec::new(), Vec::new());
+        let mut ret = SizedPartitions { items, runs, steps, choices, front, remaining, buffer };
+        if !remaining.is_zero() {
+            ret.choices = ret.steps.iter().flat_map(|&(_, k)| 0..k).collect();
+            ret.front = vec![0; items.len()];
+            decode(&ret.runs, &ret.choices, &mut ret.front);
+        }
+        ret
+    }
+
+    /// Creates an iterator over the perfect matchings of `items`, the ways to split them into unordered pairs.
+    /// 
+    /// If `items` is empty or has an odd length, the iterator will produce no values.
+    /// 
+    /// # Examples
+    /// 
+    /// ```
+    /// use gen_combinations::SizedPartitions;
+    /// 
+    /// let items = [1, 2, 3, 4];
+    /// for matching in SizedPartitions::matchings(&items) {
+    ///     println!("{:?}", matching);
+    ///     // [[1, 2], [3, 4]]
+    ///     // [[1, 3], [2, 4]]
+    ///     // [[1, 4], [2, 3]]
+    /// }
+    /// ```
+    pub fn matchings(items: &'a [T]) -> SizedPartitions<'a, T> {
+        let pairs = if items.len() % 2 == 0 { items.len() / 2 } else { 0 };
+        SizedPartitions::new(items, &vec![2; pairs])
+    }
+
+    /// Creates an iterator over the partitions of `items` into blocks with the given `sizes`, starting at the
+    /// partition with the given `rank` (see [`rank_sized_partition`]).
+    /// 
+    /// If `rank` is past the last partition, the iterator will produce no values.
+    /// 
+    /// [`rank_sized_partition`]: fn.rank_sized_partition.html
+    pub fn from_rank(items: &'a [T], sizes: &[usize], rank: usize) -> SizedPartitions<'a, T> {
+        let mut ret = SizedPartitions::new(items, sizes);
+        if rank > 0 {
+            ret.nth(rank - 1);
+        }
+        ret
+    }
+
+    /// Returns the number of partitions left, or `None` if it does not fit in a `usize` and [`len`] would panic.
+    /// 
+    /// [`len`]: https://doc.rust-lang.org/std/iter/trait.ExactSizeIterator.html#method.len
+    pub fn remaining(&self) -> Option<usize> {
+        self.remaining.get()
+    }
+
+    /// Returns the block of each item in the next partition, as a position in `sizes`, or an empty slice if there
+    /// are no partitions left.
+    pub fn indices(&self) -> &[usize] {
+        &self.front
+    }
+
+    /// Advances the iterator and returns the next partition, like [`next`], but in a buffer owned by the iterator
+    /// instead of a newly allocated `Vec`.
+    /// 
+    /// [`next`]: https://doc.rust-lang.org/std/iter/trait.Iterator.html#tymethod.next
+    pub fn next_slice(&mut self) -> Option<&[Vec<&'a T>]> {
+        let mut buffer = std::mem::take(&mut self.buffer);
+        let more = self.next_into(&mut buffer);
+        self.buffer = buffer;
+        if more {
+            Some(&self.buffer)
+        } else {
+            None
+        }
+    }
+
+    /// Advances the iterator and writes the next partition into `partition`, replacing its contents but reusing the
+    /// `Vec`s of its blocks.
+    /// 
+    /// Returns `false`, leaving `partition` unchanged, if there are no partitions left.
+    pub fn next_into(&mut self, partition: &mut Vec<Vec<&'a T>>) -> bool {
+        if self.remaining.is_zero() {
+            return false;
+        }
+        partition.resize_with(self.runs.iter().map(|(_, slots)| slots.len()).sum(), Vec::new);
+        for block in partition.iter_mut() {
+            block.clear();
+        }
+        for (item, &b) in self.items.iter().zip(&self.front) {
+            partition[b].push(item);
+        }
+        self.step();
+        true
+    }
+
+    fn count_remaining(&self) -> Option<u128> {
+        Some(wide_count(&self.steps)? - wide_grouped_rank(&self.steps, &self.choices)?)
+    }
+}
+
+impl<T> Ranked for SizedPartitions<'_, T> {
+    fn left(&self) -> Remaining {
+        self.remaining
+    }
+
+    fn rank(&self) -> Option<u128> {
+        wide_grouped_rank(&self.steps, &self.choices)
+    }
+
+    fn jump(&mut self, rank: u128, by: usize) {
+        grouped_unrank_into(&self.steps, &mut self.choices, rank);
+        decode(&self.runs, &self.choices, &mut self.front);
+        self.remaining = self.remaining.consumed(by, || Some(wide_count(&self.steps)? - rank));
+    }
+
+    fn step(&mut self) {
+        if self.remaining.is_one() {
+            self.exhaust();
+            return;
+        }
+        grouped_successor(self.steps.iter().copied(), &mut self.choices);
+        decode(&self.runs, &self.choices, &mut self.front);
+        self.remaining = self.remaining.consumed(1, || self.count_remaining());
+    }
+
+    fn exhaust(&mut self) {
+        self.front.clear();
+        self.remaining = Remaining::zero();
+    }
+}
+
+impl<T> Clone for SizedPartitions<'_, T> {
+    fn clone(&self) -> Self {
+        SizedPartitions {
+            items: self.items,
+            runs: self.runs.clone(),
+            steps: self.steps.clone(),
+            choices: self.choices.clone(),
+            front: self.front.clone(),
+            remaining: self.remaining,
+            buffer: Vec::new(),
+        }
+    }
+}
+
+impl<'a, T> Iterator for SizedPartitions<'a, T> {
+    type Item = Vec<Vec<&'a T>>;
+
+    fn next(&mut self) -> Option<Vec<Vec<&'a T>>> {
+        let mut ret = Vec::new();
+        if self.next_into(&mut ret) {
+            Some(ret)
+        } else {
+            None
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.remaining.size_hint()
+    }
+
+    fn count(self) -> usize {
+        self.remaining.get().expect("more than usize::MAX partitions")
+    }
+
+    fn nth(&mut self, n: usize) -> Option<Vec<Vec<&'a T>>> {
+        if self.skip_ahead(n) {
+            self.next()
+        } else {
+            None
+        }
+    }
+}
+
+impl<T> ExactSizeIterator for SizedPartitions<'_, T> {}
+
+impl<T> FusedIterator for SizedPartitions<'_, T> {}
+
+/// Returns the number of partitions of `sizes.iter().sum()` items into blocks with the given `sizes`, where blocks of
+/// the same size are interchangeable, or `None` if it does not fit in a `usize`.
+/// 
+/// This is `n!` divided by the factorial of each size and of the number of blocks with each size. It is 0 if `sizes`
+/// is empty or any size is 0, as for [`SizedPartitions`].
+/// 
+/// # Examples
+/// 
+/// ```
+/// // 4 teams of 3
+/// assert_eq!(gen_combinations::count_sized_partitions(&[3, 3, 3, 3]), Some(15400));
+/// // perfect matchings of 6 items
+/// assert_eq!(gen_combinations::count_sized_partitions(&[2, 2, 2]), Some(15));
+/// ```
+/// 
+/// [`SizedPartitions`]: struct.SizedPartitions.html
+pub fn count_sized_partitions(sizes: &[usize]) -> Option<usize> {
+    if !valid(sizes) {
+        return Some(0);
+    }
+    narrow(wide_count(&plan(sizes).1)?)
+}
+
+/// Returns the rank of a partition into blocks with the given `sizes`, given as the block of each item as a position
+/// in `sizes`.
+/// 
+/// Blocks of the same size may be given in any order. Returns `None` if `blocks` does not describe such a partition, or
+/// if the rank does not fit in a `usize`.
+/// 
+/// # Examples
+/// 
+/// ```
+/// assert_eq!(gen_combinations::rank_sized_partition(&[2, 2], &[0, 1, 0, 1]), Some(1));
+/// assert_eq!(gen_combinations::rank_sized_partition(&[2, 2], &[1, 0, 1, 0]), Some(1));
+/// assert_eq!(gen_combinations::rank_sized_partition(&[2, 2], &[0, 0, 0, 1]), None);
+/// ```
+pub fn rank_sized_partition(sizes: &[usize], blocks: &[usize]) -> Option<usize> {
+    let mut counts = vec![0; sizes.len()];
+    for &b in blocks {
+        *counts.get_mut(b)? += 1;
+    }
+    if !valid(sizes) || counts != sizes {
+        return None;
+    }
+    let (runs, steps) = plan(sizes);
+    narrow(wide_grouped_rank(&steps, &encode(&runs, blocks))?)
+}
+
+/// Returns the block of each item, as a position in `sizes`, in the partition into blocks with the given `sizes` that
+/// has the given `rank`.
+/// 
+/// This is the inverse of [`rank_sized_partition`]. Returns `None` if there are not more than `rank` such partitions.
+/// 
+/// # Examples
+/// 
+/// ```
+/// assert_eq!(gen_combinations::unrank_sized_partition(&[2, 2], 1), Some(vec![0, 1, 0, 1]));
+/// assert_eq!(gen_combinations::unrank_sized_partition(&[2, 2], 3), None);
+/// ```
+/// 
+/// [`rank_sized_partition`]: fn.rank_sized_partition.html
+pub fn unrank_sized_partition(sizes: &[usize], rank: usize) -> Option<Vec<usize>> {
+    if count_sized_partitions(sizes).is_some_and(|count| rank >= count) {
+        return None;
+    }
+    let (runs, steps) = plan(sizes);
+    let mut choices = vec![0; steps.iter().map(|&(_, k)| k).sum()];
+    grouped_unrank_into(&steps, &mut choices, rank as u128);
+    let mut blocks = vec![0; sizes.iter().sum()];
+    decode(&runs, &choices, &mut blocks);
+    Some(blocks)
+}
+
+/// Each size of block, with the positions in `sizes` of the blocks that have it.
+type Runs = Vec<(usize, Vec<usize>)>;
+
+fn valid(sizes: &[usize]) -> bool {
+    !sizes.is_empty() && !sizes.contains(&0)
+}
+
+/// Groups the positions in `sizes` by size and returns them along with the combinations that choose a partition.
+/// 
+/// The items for all the blocks of a size are chosen first, out of those left. Then each block in turn takes the first
+/// of those items that are left, and chooses the rest of its items from them, so that no two orders of the blocks
+/// give the same partition. `sizes` must all be at least 1.
+fn plan(sizes: &[usize]) -> (Runs, Vec<(usize, usize)>) {
+    let mut runs: Runs = Vec::new();
+    for (b, &size) in sizes.iter().enumerate() {
+        match runs.iter_mut().find(|(s, _)| *s == size) {
+            Some((_, slots)) => slots.push(b),
+            None => runs.push((size, vec![b])),
+        }
+    }
+    let mut steps = Vec::new();
+    let mut left: usize = sizes.iter().sum();
+    for (size, slots) in &runs {
+        let total = size * slots.len();
+        steps.push((left, total));
+        left -= total;
+        for i in 0..slots.len() {
+            steps.push((total - i * size - 1, size - 1));
+        }
+    }
+    (runs, steps)
+}
+
+/// Fills `blocks` with the block of each item in the partition chosen by the combinations in `choices`.
+fn decode(runs: &[(usize, Vec<usize>)], choices: &[usize], blocks: &mut [usize]) {
+    let mut rest: Vec<usize> = (0..blocks.len()).collect();
+    let mut choices = choices;
+    for (size, slots) in runs {
+        let (chosen, tail) = choices.split_at(size * slots.len());
+        let mut run = take(&mut rest, chosen);
+        choices = tail;
+        for &b in slots {
+            let (chosen, tail) = choices.split_at(size - 1);
+            blocks[run.remove(0)] = b;
+            for item in take(&mut run, chosen) {
+                blocks[item] = b;
+            }
+            choices = tail;
+        }
+    }
+}
+
+/// Returns the combinations that choose the partition where each item is in the given block.
+fn encode(runs: &[(usize, Vec<usize>)], blocks: &[usize]) -> Vec<usize> {
+    let mut rest: Vec<usize> = (0..blocks.len()).collect();
+    let mut choices = Vec::new();
+    let positions = |list: &[usize], f: &dyn Fn(usize) -> bool| -> Vec<usize> {
+        list.iter().enumerate().filter(|&(_, &item)| f(item)).map(|(p, _)| p).collect()
+    };
+    for (_, slots) in runs {
+        let chosen = positions(&rest, &|item| slots.contains(&blocks[item]));
+        let mut run = take(&mut rest, &chosen);
+        choices.extend(chosen);
+        while !run.is_empty() {
+            let b = blocks[run.remove(0)];
+            let chosen = positions(&run, &|item| blocks[item] == b);
+            take(&mut run, &chosen);
+            choices.extend(chosen);
+        }
+    }
+    choices
+}
+
+/// Removes the items at the strictly increasing `positions` from `list` and returns them.
+fn take(list: &mut Vec<usize>, positions: &[usize]) -> Vec<usize> {
+    let mut taken = Vec::with_capacity(positions.len());
+    let mut positions = positions.iter().peekable();
+    let mut kept = 0;
+    for i in 0..list.len() {
+        if positions.next_if_eq(&&i).is_some() {
+            taken.push(list[i]);
+        } else {
+            list[kept] = list[i];
+            kept += 1;
+        }
+    }
+    list.truncate(kept);
+    taken
+}
+
+#[test]
+fn set_partitions() {
+    fn owned(p: Vec<Vec<&i32>>) -> Vec<Vec<i32>> {
+        p.into_iter().map(|block| block.into_iter().copied().collect()).collect()
+    }
+
+    let items = [1, 2, 3, 4];
+    let all: Vec<Vec<Vec<i32>>> = SetPartitions::new(&items).map(owned).collect();
+    assert_eq!(all.len(), 15);
+    assert_eq!(all[0], [vec![1, 2, 3, 4]]);
+    assert_eq!(all[1], [vec![1, 2, 3], vec![4]]);
+    assert_eq!(all[14], [vec![1], vec![2], vec![3], vec![4]]);
+
+    // every partition appears once, with its blocks in order of their first items
+    let mut sorted = all.clone();
+    sorted.sort();
+    sorted.dedup();
+    assert_eq!(sorted.len(), 15);
+    assert!(all.iter().all(|p| p.windows(2).all(|w| w[0][0] < w[1][0])));
+
+    for m in 0..=5 {
+        let exact: Vec<_> = all.iter().filter(|p| p.len() == m).collect();
+        let at_most: Vec<_> = all.iter().filter(|p| p.len() <= m).collect();
+        let mut e = SetPartitions::with_blocks(&items, m);
+        assert_eq!(e.len(), exact.len());
+        assert_eq!(stirling2(4, m), Some(exact.len()));
+        for p in exact {
+            assert_eq!(e.next().map(owned).as_ref(), Some(p));
+        }
+        assert_eq!(e.next(), None);
+        assert_eq!(SetPartitions::with_max_blocks(&items, m).count(), at_most.len());
+    }
+    assert_eq!(SetPartitions::<i32>::new(&[]).next(), None);
+}
+
+#[test]
+fn set_partitions_nth() {
+    let items = [0; 7];
+    for &(m, exact) in &[(7, false), (3, false), (3, true), (1, true), (7, true)] {
+        let p = if exact { SetPartitions::with_blocks(&items, m) } else { SetPartitions::with_max_blocks(&items, m) };
+        let mut strings = Vec::new();
+        let mut q = p.clone();
+        while !q.indices().is_empty() {
+            strings.push(q.indices().to_vec());
+            q.next_slice();
+        }
+        for (r, rgs) in strings.iter().enumerate() {
+            let mut skipped = p.clone();
+            if r > 0 {
+                skipped.nth(r - 1);
+            }
+            assert_eq!(skipped.indices(), &rgs[..]);
+            assert_eq!(skipped.len(), strings.len() - r);
+        }
+    }
+    assert_eq!(bell(7), Some(877));
+    assert_eq!(SetPartitions::new(&items).count(), 877);
+
+    // more than a u128 holds, but the iterator can still step and skip
+    let items = [0; 200];
+    let mut p = SetPartitions::new(&items);
+    assert_eq!(bell(200), None);
+    assert_eq!(p.size_hint(), (usize::MAX, None));
+    assert_eq!(p.nth(5).map(|p| p.len()), Some(2));
+    assert_eq!(p.indices()[195..], [0, 0, 1, 0, 1]);
+}
+
+#[test]
+fn sized_partitions() {
+    // every assignment of 6 items to blocks of sizes 2, 1, 2 and 1, up to swapping blocks of the same size
+    let sizes = [2, 1, 2, 1];
+    let mut expected = Vec::new();
+    for code in 0..4usize.pow(6) {
+        let blocks: Vec<usize> = (0..6).map(|i| code / 4usize.pow(5 - i) % 4).collect();
+        let counts: Vec<usize> = (0..4).map(|b| blocks.iter().filter(|&&x| x == b).count()).collect();
+        let first = |b: usize| blocks.iter().position(|&x| x == b);
+        if counts == sizes && first(0) < first(2) && first(1) < first(3) {
+            expected.push(blocks);
+        }
+    }
+    assert_eq!(count_sized_partitions(&sizes), Some(expected.len()));
+    assert_eq!(expected.len(), 6 * 5 * 4 * 3 / 2 / 2 / 2);
+
+    let items = [0, 1, 2, 3, 4, 5];
+    let mut p = SizedPartitions::new(&items, &sizes);
+    let mut seen = Vec::new();
+    for r in 0..expected.len() {
+        let blocks = p.indices().to_vec();
+        assert_eq!(rank_sized_partition(&sizes, &blocks), Some(r));
+        assert_eq!(unrank_sized_partition(&sizes, r).as_ref(), Some(&blocks));
+        assert_eq!(SizedPartitions::from_rank(&items, &sizes, r).indices(), &blocks[..]);
+        assert_eq!(p.len(), expected.len() - r);
+        let partition = p.next().unwrap();
+        for (b, block) in partition.iter().enumerate() {
+            assert_eq!(block.len(), sizes[b]);
+            assert!(block.iter().all(|&&item| blocks[item] == b));
+        }
+        seen.push(blocks);
+    }
+    assert_eq!(p.next(), None);
+    seen.sort();
+    expected.sort();
+    assert_eq!(seen, expected);
+
+    // swapping blocks of the same size gives the same partition
+    assert_eq!(rank_sized_partition(&sizes, &[2, 3, 0, 1, 2, 0]), rank_sized_partition(&sizes, &[0, 1, 2, 3, 0, 2]));
+    assert_eq!(rank_sized_partition(&sizes, &[0, 0, 0, 1, 2, 3]), None);
+    assert_eq!(rank_sized_partition(&sizes, &[0, 0, 4, 1, 2, 3]), None);
+    assert_eq!(SizedPartitions::new(&items, &[2, 3]).next(), None);
+
+    // later blocks with more choices than a u128 holds
+    let sizes = [1, 150, 150];
+    let first = unrank_sized_partition(&sizes, 0).unwrap();
+    assert_eq!(rank_sized_partition(&sizes, &first), Some(0));
+    assert_eq!(rank_sized_partition(&sizes, &unrank_sized_partition(&sizes, 9).unwrap()), Some(9));
+    let many: Vec<usize> = (0..301).collect();
+    let mut p = SizedPartitions::from_rank(&many, &sizes, 3);
+    let mut skipped = SizedPartitions::new(&many, &sizes);
+    assert_eq!(skipped.nth(3), p.next());
+    assert_eq!(SizedPartitions::new(&items, &[0, 6]).next(), None);
+}
+
+#[test]
+fn perfect_matchings() {
+    let items = [0; 10];
+    let mut m = SizedPartitions::matchings(&items);
+    assert_eq!(m.len(), 9 * 7 * 5 * 3);
+    let mut seen = Vec::new();
+    while !m.indices().is_empty() {
+        seen.push(m.indices().to_vec());
+        assert!(m.next_slice().unwrap().iter().all(|pair| pair.len() == 2));
+    }
+    seen.sort();
+    seen.dedup();
+    assert_eq!(seen.len(), 945);
+    assert_eq!(SizedPartitions::matchings(&items[..9]).next(), None);
+    assert_eq!(SizedPartitions::<i32>::matchings(&[]).next(), None);
+
+    // more matchings than a u128 holds
+    let items = [0; 100];
+    let mut m = SizedPartitions::matchings(&items);
+    assert_eq!(m.size_hint(), (usize::MAX, None));
+    m.nth(2);
+    assert_eq!(m.indices()[..4], [0, 0, 1, 1]);
+    assert_eq!(m.indices()[94..], [47, 48, 47, 48, 49, 49]);
+}
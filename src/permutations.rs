@@ -0,0 +1,1617 @@
+//! Iterators over permutations of items, where the order of the items matters.
+//! 
+//! A `k`-permutation of `n` items is an arrangement of `k` distinct items out of the `n`, written as the indices of
+//! the items in the order they are arranged. When `k` is `n`, these are the full permutations of the items.
+//! [`Permutations`] produces them in lexicographic order, and [`PlainChanges`] in the order of the
+//! Steinhaus–Johnson–Trotter algorithm, where each permutation of the same items differs from the previous one by
+//! swapping two adjacent items. [`MultisetPermutations`] treats equal items as interchangeable, and [`Derangements`]
+//! produces only the full permutations that move every item out of its own position.
+//! 
+//! [`Permutations`]: struct.Permutations.html
+//! [`PlainChanges`]: struct.PlainChanges.html
+//! [`MultisetPermutations`]: struct.MultisetPermutations.html
+//! [`Derangements`]: struct.Derangements.html
+
+use crate::cursor::Cursor;
+use crate::multiset::group_by_key;
+use crate::remaining::{Ranked, Remaining};
+use crate::natural::Natural;
+use crate::{binomial, narrow, unrank_into, wide_rank, Order};
+use std::collections::BTreeMap;
+use std::iter::{once, FusedIterator};
+
+/// Iterates over all possible `k`-permutations of items in lexicographic order.
+/// 
+/// The permutations are of immutable references to the items, with the same interface as
+/// [`CombinationIterator`]: [`next_slice`] and [`next_into`] avoid allocating a `Vec` for each one, and [`nth`] jumps
+/// ahead by unranking.
+/// 
+/// # Examples
+/// 
+/// ```
+/// use gen_combinations::permutations::Permutations;
+/// 
+/// let items = [1, 2, 3];
+/// for perm in Permutations::new(&items, 2) {
+///     println!("{:?}", perm);
+///     // [1, 2]
+///     // [1, 3]
+///     // [2, 1]
+///     // [2, 3]
+///     // [3, 1]
+///     // [3, 2]
+/// }
+/// ```
+/// 
+/// [`CombinationIterator`]: ../struct.CombinationIterator.html
+/// [`next_slice`]: #method.next_slice
+/// [`next_into`]: #method.next_into
+/// [`nth`]: https://doc.rust-lang.org/std/iter/trait.Iterator.html#method.nth
+#[derive(Debug)]
+pub struct Permutations<'a, T> {
+    items: &'a [T],
+    k: usize,
+    perm: Vec<usize>, // the next permutation, followed by the unused indices in increasing order
+    remaining: Remaining,
+    buffer: Vec<&'a T>, // reused by next_slice
+}
+
+impl<'a, T> Permutations<'a, T> {
+    /// Creates an iterator over the permutations of `k` out of `items`.
+    /// 
+    /// If `k` is 0 or greater than `items.len()`, the iterator will produce no values.
+    pub fn new(items: &[T], k: usize) -> Permutations<'_, T> {
+        Permutations::from_rank(items, k, 0)
+    }
+
+    /// Creates an iterator over the permutations of `k` out of `items`, starting at the permutation with the given
+    /// lexicographic `rank` (see [`rank`]).
+    /// 
+    /// If `rank` is past the last permutation, the iterator will produce no values.
+    /// 
+    /// [`rank`]: fn.rank.html
+    pub fn from_rank(items: &[T], k: usize, rank: usize) -> Permutations<'_, T> {
+        let n = items.len();
+        let mut ret = Permutations { items, k, perm: Vec::new(), remaining: Remaining::zero(), buffer: Vec::new() };
+        if k > 0 && k <= n && wide_count(n, k).is_none_or(|count| (rank as u128) < count) {
+            lex_unrank_into(&mut ret.perm, n, k, rank as u128);
+            ret.remaining = Remaining::new(wide_count(n, k).map(|count| count - rank as u128));
+        }
+        ret
+    }
+
+    /// Returns the number of permutations left, or `None` if it does not fit in a `usize` and [`len`] would panic.
+    /// 
+    /// [`len`]: https://doc.rust-lang.org/std/iter/trait.ExactSizeIterator.html#method.len
+    pub fn remaining(&self) -> Option<usize> {
+        self.remaining.get()
+    }
+
+    /// Returns the indices into `items` of the next permutation, or an empty slice if there are no permutations left.
+    pub fn indices(&self) -> &[usize] {
+        &self.perm[..self.perm.len().min(self.k)]
+    }
+
+    /// Advances the iterator and returns the next permutation, like [`next`], but in a buffer owned by the iterator
+    /// instead of a newly allocated `Vec`.
+    /// 
+    /// [`next`]: https://doc.rust-lang.org/std/iter/trait.Iterator.html#tymethod.next
+    pub fn next_slice(&mut self) -> Option<&[&'a T]> {
+        let mut buffer = std::mem::take(&mut self.buffer);
+        let more = self.next_into(&mut buffer);
+        self.buffer = buffer;
+        if more {
+            Some(&self.buffer)
+        } else {
+            None
+        }
+    }
+
+    /// Advances the iterator and writes the next permutation into `perm`, replacing its contents.
+    /// 
+    /// Returns `false`, leaving `perm` unchanged, if there are no permutations left.
+    pub fn next_into(&mut self, perm: &mut Vec<&'a T>) -> bool {
+        if self.remaining.is_zero() {
+            return false;
+        }
+        let items = self.items;
+        perm.clear();
+        perm.extend(self.indices().iter().map(|i| &items[*i]));
+        self.step();
+        true
+    }
+
+    /// Updates the remaining count after moving forward by `by` permutations.
+    fn consume(&mut self, by: usize) {
+        self.remaining = self.remaining.consumed(by, || {
+            let n = self.items.len();
+            Some(wide_count(n, self.k)? - wide_lex_rank(n, self.indices())?)
+        });
+    }
+}
+
+impl<T> Ranked for Permutations<'_, T> {
+    fn left(&self) -> Remaining {
+        self.remaining
+    }
+
+    fn rank(&self) -> Option<u128> {
+        wide_lex_rank(self.items.len(), self.indices())
+    }
+
+    fn jump(&mut self, rank: u128, by: usize) {
+        lex_unrank_into(&mut self.perm, self.items.len(), self.k, rank);
+        self.consume(by);
+    }
+
+    fn step(&mut self) {
+        // reversing the unused indices puts them in decreasing order, so the next full permutation changes the first k
+        self.perm[self.k..].reverse();
+        if self.remaining.is_one() || !next_permutation(&mut self.perm) {
+            self.exhaust();
+        } else {
+            self.consume(1);
+        }
+    }
+
+    fn exhaust(&mut self) {
+        self.perm.clear();
+        self.remaining = Remaining::zero();
+    }
+}
+
+impl<T> Clone for Permutations<'_, T> {
+    fn clone(&self) -> Self {
+        Permutations {
+            items: self.items,
+            k: self.k,
+            perm: self.perm.clone(),
+            remaining: self.remaining,
+            buffer: Vec::new(),
+        }
+    }
+}
+
+impl<'a, T> Iterator for Permutations<'a, T> {
+    type Item = Vec<&'a T>;
+
+    fn next(&mut self) -> Option<Vec<&'a T>> {
+        if self.remaining.is_zero() {
+            return None;
+        }
+        let ret = self.indices().iter().map(|i| &self.items[*i]).collect();
+        self.step();
+        Some(ret)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.remaining.size_hint()
+    }
+
+    fn count(self) -> usize {
+        self.remaining.get().expect("more than usize::MAX permutations")
+    }
+
+    fn nth(&mut self, n: usize) -> Option<Vec<&'a T>> {
+        if self.skip_ahead(n) {
+            self.next()
+        } else {
+            None
+        }
+    }
+}
+
+impl<T> ExactSizeIterator for Permutations<'_, T> {}
+
+impl<T> FusedIterator for Permutations<'_, T> {}
+
+/// Iterates over all possible `k`-permutations of items in plain-changes order, which is the order of the
+/// Steinhaus–Johnson–Trotter algorithm.
+/// 
+/// The combinations of `k` items are taken in lexicographic order, as by [`CombinationIterator`], and for each one
+/// every arrangement of its items is produced before moving on to the next. Within a combination, each arrangement
+/// differs from the previous one by swapping two adjacent items, which [`swapped`] reports. This uses Knuth's
+/// Algorithm P (The Art of Computer Programming, section 7.2.1.2).
+/// 
+/// # Examples
+/// 
+/// ```
+/// use gen_combinations::permutations::PlainChanges;
+/// 
+/// let items = ['a', 'b', 'c'];
+/// let mut perms = PlainChanges::new(&items, 3);
+/// while let Some(perm) = perms.next() {
+///     println!("{:?} then swap at {:?}", perm, perms.swapped());
+///     // ['a', 'b', 'c'] then swap at Some(1)
+///     // ['a', 'c', 'b'] then swap at Some(0)
+///     // ['c', 'a', 'b'] then swap at Some(1)
+///     // ['c', 'b', 'a'] then swap at Some(0)
+///     // ['b', 'c', 'a'] then swap at Some(1)
+///     // ['b', 'a', 'c'] then swap at None
+/// }
+/// ```
+/// 
+/// [`CombinationIterator`]: ../struct.CombinationIterator.html
+/// [`swapped`]: #method.swapped
+#[derive(Debug)]
+pub struct PlainChanges<'a, T> {
+    items: &'a [T],
+    combos: Cursor, // the combinations after the current one
+    perm: Vec<usize>, // the next permutation
+    counters: Vec<(usize, bool)>, // Knuth's c_j and whether o_j is positive
+    swap: Option<usize>, // the swap that led to the next permutation
+    remaining: Remaining,
+    buffer: Vec<&'a T>, // reused by next_slice
+}
+
+impl<'a, T> PlainChanges<'a, T> {
+    /// Creates an iterator over the permutations of `k` out of `items` in plain-changes order.
+    /// 
+    /// If `k` is 0 or greater than `items.len()`, the iterator will produce no values.
+    pub fn new(items: &[T], k: usize) -> PlainChanges<'_, T> {
+        let mut combos = Cursor::new(items.len(), k);
+        let perm = combos.front().to_vec();
+        if !combos.is_empty() {
+            combos.step_front();
+        }
+        let remaining = if perm.is_empty() { Remaining::zero() } else { Remaining::new(wide_count(items.len(), k)) };
+        PlainChanges {
+            items,
+            combos,
+            perm,
+            counters: vec![(0, true); k],
+            swap: None,
+            remaining,
+            buffer: Vec::new(),
+        }
+    }
+
+    /// Returns the number of permutations left, or `None` if it does not fit in a `usize` and [`len`] would panic.
+    /// 
+    /// [`len`]: https://doc.rust-lang.org/std/iter/trait.ExactSizeIterator.html#method.len
+    pub fn remaining(&self) -> Option<usize> {
+        self.remaining.get()
+    }
+
+    /// Returns the indices into `items` of the next permutation, or an empty slice if there are no permutations left.
+    pub fn indices(&self) -> &[usize] {
+        &self.perm
+    }
+
+    /// Returns the position `i` such that the next permutation is the previous one with the items at `i` and `i + 1`
+    /// swapped, or `None` if the next permutation is the first of its combination or there are none left.
+    pub fn swapped(&self) -> Option<usize> {
+        self.swap
+    }
+
+    /// Advances the iterator and returns the next permutation, like [`next`], but in a buffer owned by the iterator
+    /// instead of a newly allocated `Vec`.
+    /// 
+    /// [`next`]: https://doc.rust-lang.org/std/iter/trait.Iterator.html#tymethod.next
+    pub fn next_slice(&mut self) -> Option<&[&'a T]> {
+        let mut buffer = std::mem::take(&mut self.buffer);
+        let more = self.next_into(&mut buffer);
+        self.buffer = buffer;
+        if more {
+            Some(&self.buffer)
+        } else {
+            None
+        }
+    }
+
+    /// Advances the iterator and writes the next permutation into `perm`, replacing its contents.
+    /// 
+    /// Returns `false`, leaving `perm` unchanged, if there are no permutations left.
+    pub fn next_into(&mut self, perm: &mut Vec<&'a T>) -> bool {
+        if self.remaining.is_zero() {
+            return false;
+        }
+        let items = self.items;
+        perm.clear();
+        perm.extend(self.perm.iter().map(|i| &items[*i]));
+        self.step();
+        true
+    }
+
+    /// Updates the remaining count after moving forward by `by` permutations.
+    fn consume(&mut self, by: usize) {
+        self.remaining = self.remaining.consumed(by, || {
+            let n = self.items.len();
+            let k = self.perm.len();
+            plain_changes_rank(n, &self.perm).and_then(|rank| Some(wide_count(n, k)? - rank as u128))
+        });
+    }
+}
+
+impl<T> Ranked for PlainChanges<'_, T> {
+    fn left(&self) -> Remaining {
+        self.remaining
+    }
+
+    fn rank(&self) -> Option<u128> {
+        plain_changes_rank(self.items.len(), &self.perm).map(|rank| rank as u128)
+    }
+
+    fn jump(&mut self, rank: u128, by: usize) {
+        let n = self.items.len();
+        let k = self.perm.len();
+        let (perm, combo, counters) = plain_unrank_state(n, k, rank);
+        self.combos = Cursor::starting_at(n, combo, Order::Lexicographic);
+        self.combos.step_front();
+        // unless this is the first arrangement of its items, it is one swap away from the previous one
+        self.swap = if rank % wide_count(k, k).unwrap_or(u128::MAX) == 0 {
+            None
+        } else {
+            let before = plain_unrank_state(n, k, rank - 1).0;
+            (0..k - 1).find(|&i| before[i] != perm[i])
+        };
+        self.perm = perm;
+        self.counters = counters;
+        self.consume(by);
+    }
+
+    fn step(&mut self) {
+        if self.remaining.is_one() {
+            self.exhaust();
+            return;
+        }
+        self.swap = plain_change(&mut self.perm, &mut self.counters);
+        if self.swap.is_none() {
+            if self.combos.is_empty() {
+                self.exhaust();
+                return;
+            }
+            self.perm.copy_from_slice(self.combos.front());
+            self.combos.step_front();
+            self.counters.fill((0, true));
+        }
+        self.consume(1);
+    }
+
+    fn exhaust(&mut self) {
+        self.perm.clear();
+        self.swap = None;
+        self.remaining = Remaining::zero();
+    }
+}
+
+impl<T> Clone for PlainChanges<'_, T> {
+    fn clone(&self) -> Self {
+        PlainChanges {
+            items: self.items,
+            combos: self.combos.clone(),
+            perm: self.perm.clone(),
+            counters: self.counters.clone(),
+            swap: self.swap,
+            remaining: self.remaining,
+            buffer: Vec::new(),
+        }
+    }
+}
+
+impl<'a, T> Iterator for PlainChanges<'a, T> {
+    type Item = Vec<&'a T>;
+
+    fn next(&mut self) -> Option<Vec<&'a T>> {
+        if self.remaining.is_zero() {
+            return None;
+        }
+        let ret = self.perm.iter().map(|i| &self.items[*i]).collect();
+        self.step();
+        Some(ret)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.remaining.size_hint()
+    }
+
+    fn count(self) -> usize {
+        self.remaining.get().expect("more than usize::MAX permutations")
+    }
+
+    fn nth(&mut self, n: usize) -> Option<Vec<&'a T>> {
+        if self.skip_ahead(n) {
+            self.next()
+        } else {
+            None
+        }
+    }
+}
+
+impl<T> ExactSizeIterator for PlainChanges<'_, T> {}
+
+impl<T> FusedIterator for PlainChanges<'_, T> {}
+
+/// Iterates over all distinct permutations of items that may contain duplicates.
+/// 
+/// Equal items are interchangeable, so each arrangement of values is produced exactly once, and there are
+/// [`multinomial`] of them. Each permutation takes constant time to find, using the loopless algorithm of Williams
+/// ("Loopless generation of multiset permutations using a constant number of variables by prefix shifts", 2009), so
+/// the order is that of prefix shifts rather than lexicographic order. Only the first of each group of equal items is
+/// ever produced.
+/// 
+/// # Examples
+/// 
+/// ```
+/// use gen_combinations::permutations::MultisetPermutations;
+/// 
+/// let letters = ['A', 'A', 'B'];
+/// for perm in MultisetPermutations::new(&letters) {
+///     println!("{:?}", perm);
+///     // ['B', 'A', 'A']
+///     // ['A', 'B', 'A']
+///     // ['A', 'A', 'B']
+/// }
+/// ```
+/// 
+/// [`multinomial`]: fn.multinomial.html
+#[derive(Debug)]
+pub struct MultisetPermutations<'a, T> {
+    groups: Vec<&'a T>, // one representative item per group
+    links: Vec<(usize, usize)>, // the group of each node of the linked list, and the node after it or NIL
+    head: usize,
+    i: usize, // Williams' i and j
+    j: usize,
+    remaining: Remaining,
+    done: bool,
+    buffer: Vec<&'a T>, // reused by next_slice
+}
+
+const NIL: usize = usize::MAX;
+
+impl<'a, T: Ord> MultisetPermutations<'a, T> {
+    /// Creates an iterator over the distinct permutations of `items`, treating equal items as interchangeable.
+    /// 
+    /// If `items` is empty, the iterator will produce no values.
+    pub fn new(items: &'a [T]) -> MultisetPermutations<'a, T> {
+        MultisetPermutations::new_by_key(items, |item| item)
+    }
+}
+
+impl<'a, T> MultisetPermutations<'a, T> {
+    /// Creates an iterator over the distinct permutations of `items`, treating items with equal keys as
+    /// interchangeable.
+    /// 
+    /// If `items` is empty, the iterator will produce no values.
+    pub fn new_by_key<K, F>(items: &'a [T], key: F) -> MultisetPermutations<'a, T>
+    where
+        K: Ord,
+        F: FnMut(&'a T) -> K,
+    {
+        let (groups, counts) = group_by_key(items, key);
+        MultisetPermutations::with_groups(groups, &counts)
+    }
+
+    /// Creates an iterator over the distinct permutations of a multiset given as `(item, count)` pairs, where each item
+    /// appears `count` times.
+    /// 
+    /// If the counts are all 0, the iterator will produce no values.
+    /// 
+    /// # Examples
+    /// 
+    /// ```
+    /// use gen_combinations::permutations::MultisetPermutations;
+    /// 
+    /// let perms = MultisetPermutations::from_counts(&[('A', 2), ('B', 2), ('C', 1)]);
+    /// assert_eq!(perms.len(), 30);
+    /// ```
+    pub fn from_counts(pairs: &'a [(T, usize)]) -> MultisetPermutations<'a, T> {
+        let (groups, counts): (Vec<_>, Vec<_>) = pairs.iter().map(|(item, count)| (item, *count)).unzip();
+        MultisetPermutations::with_groups(groups, &counts)
+    }
+
+    fn with_groups(groups: Vec<&'a T>, counts: &[usize]) -> MultisetPermutations<'a, T> {
+        // the list starts in nonincreasing order of group, which is the last permutation in lexicographic order
+        let mut links: Vec<(usize, usize)> = Vec::new();
+        for (g, &count) in counts.iter().enumerate().rev() {
+            links.extend((0..count).map(|_| (g, 0)));
+        }
+        let len = links.len();
+        for (node, link) in links.iter_mut().enumerate() {
+            link.1 = if node + 1 < len { node + 1 } else { NIL };
+        }
+        let count = multinomial(counts).map(|count| count as u128);
+        let remaining = if len == 0 { Remaining::zero() } else { Remaining::new(count) };
+        MultisetPermutations {
+            groups,
+            links,
+            head: 0,
+            i: len.saturating_sub(2),
+            j: len.saturating_sub(1),
+            remaining,
+            done: len == 0,
+            buffer: Vec::new(),
+        }
+    }
+
+    /// Returns the number of permutations left, or `None` if it does not fit in a `usize` and [`len`] would panic.
+    /// 
+    /// [`len`]: https://doc.rust-lang.org/std/iter/trait.ExactSizeIterator.html#method.len
+    pub fn remaining(&self) -> Option<usize> {
+        self.remaining.get()
+    }
+
+    /// Advances the iterator and returns the next permutation, like [`next`], but in a buffer owned by the iterator
+    /// instead of a newly allocated `Vec`.
+    /// 
+    /// [`next`]: https://doc.rust-lang.org/std/iter/trait.Iterator.html#tymethod.next
+    pub fn next_slice(&mut self) -> Option<&[&'a T]> {
+        if self.done {
+            return None;
+        }
+        let mut buffer = std::mem::take(&mut self.buffer);
+        buffer.clear();
+        self.write(&mut buffer);
+        self.step();
+        self.buffer = buffer;
+        Some(&self.buffer)
+    }
+
+    fn write(&self, perm: &mut Vec<&'a T>) {
+        let mut node = self.head;
+        while node != NIL {
+            perm.push(self.groups[self.links[node].0]);
+            node = self.links[node].1;
+        }
+    }
+
+    /// Moves to the next permutation by shifting one item to the front.
+    fn step(&mut self) {
+        self.remaining = self.remaining.consumed(1, || None);
+        let links = &mut self.links;
+        let (i, j, head) = (self.i, self.j, self.head);
+        let after_j = if links.len() < 2 { NIL } else { links[j].1 };
+        if after_j == NIL && links[j].0 >= links[head].0 {
+            self.done = true;
+            return;
+        }
+        let s = if after_j != NIL && links[i].0 >= links[after_j].0 { j } else { i };
+        let t = links[s].1;
+        links[s].1 = links[t].1;
+        links[t].1 = head;
+        if links[t].0 < links[head].0 {
+            self.i = t;
+        }
+        self.j = links[self.i].1;
+        self.head = t;
+    }
+}
+
+impl<T> Clone for MultisetPermutations<'_, T> {
+    fn clone(&self) -> Self {
+        MultisetPermutations {
+            groups: self.groups.clone(),
+            links: self.links.clone(),
+            head: self.head,
+            i: self.i,
+            j: self.j,
+            remaining: self.remaining,
+            done: self.done,
+            buffer: Vec::new(),
+        }
+    }
+}
+
+impl<'a, T> Iterator for MultisetPermutations<'a, T> {
+    type Item = Vec<&'a T>;
+
+    fn next(&mut self) -> Option<Vec<&'a T>> {
+        if self.done {
+            return None;
+        }
+        let mut ret = Vec::with_capacity(self.links.len());
+        self.write(&mut ret);
+        self.step();
+        Some(ret)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.remaining.size_hint()
+    }
+}
+
+impl<T> ExactSizeIterator for MultisetPermutations<'_, T> {}
+
+impl<T> FusedIterator for MultisetPermutations<'_, T> {}
+
+/// Iterates over the derangements of items, the permutations of all of them in which no item is left in its own
+/// position, in lexicographic order.
+/// 
+/// The derangements are generated directly rather than by filtering the permutations, by backtracking that never
+/// enters a prefix with no derangements after it. [`with_forbidden`] rules out further positions for some items, as in
+/// a secret-santa draw where partners may not draw each other. There are [`subfactorial`]`(n)` derangements of `n`
+/// items, and [`nth`] jumps ahead by unranking.
+/// 
+/// # Examples
+/// 
+/// ```
+/// use gen_combinations::permutations::Derangements;
+/// 
+/// let items = [1, 2, 3];
+/// for perm in Derangements::new(&items) {
+///     println!("{:?}", perm);
+///     // [2, 3, 1]
+///     // [3, 1, 2]
+/// }
+/// ```
+/// 
+/// [`with_forbidden`]: #method.with_forbidden
+/// [`subfactorial`]: fn.subfactorial.html
+/// [`nth`]: https://doc.rust-lang.org/std/iter/trait.Iterator.html#method.nth
+#[derive(Debug)]
+pub struct Derangements<'a, T> {
+    items: &'a [T],
+    board: Board,
+    perm: Vec<usize>, // the next derangement
+    used: Vec<bool>, // whether each index is in perm
+    remaining: Remaining,
+    buffer: Vec<&'a T>, // reused by next_slice
+}
+
+impl<'a, T> Derangements<'a, T> {
+    /// Creates an iterator over the derangements of `items`.
+    /// 
+    /// If `items` has fewer than two items, the iterator will produce no values.
+    pub fn new(items: &[T]) -> Derangements<'_, T> {
+        Derangements::with_forbidden(items, &[])
+    }
+
+    /// Creates an iterator over the derangements of `items` in which, for each `(i, j)` pair of `forbidden`, position
+    /// `i` does not hold `items[j]` either.
+    /// 
+    /// Pairs with an index not less than `items.len()` are ignored. Finding each derangement takes polynomial time, by
+    /// matching the positions left to the items left. Counting them is by inclusion–exclusion over the ways to place
+    /// non-attacking rooks on the forbidden pairs, one group of rows and columns that share pairs at a time, so it is
+    /// quick for pairs such as partners ruling each other out, but slow for pairs that tie many positions together.
+    /// 
+    /// # Examples
+    /// 
+    /// ```
+    /// use gen_combinations::permutations::Derangements;
+    /// 
+    /// // Alice and Bob are partners, so neither may draw the other
+    /// let people = ["Alice", "Bob", "Carol", "Dave"];
+    /// let draws = Derangements::with_forbidden(&people, &[(0, 1), (1, 0)]);
+    /// assert_eq!(draws.len(), 4);
+    /// for draw in draws {
+    ///     assert!(draw[0] != &"Bob" && draw[1] != &"Alice");
+    /// }
+    /// ```
+    /// 
+    pub fn with_forbidden(items: &'a [T], forbidden: &[(usize, usize)]) -> Derangements<'a, T> {
+        let n = items.len();
+        let board = Board::new(n, forbidden);
+        let mut used = vec![false; n];
+        let count = board.completions(0, &used);
+        let mut ret = Derangements {
+            items,
+            board,
+            perm: Vec::new(),
+            used: Vec::new(),
+            remaining: Remaining::new(count),
+            buffer: Vec::new(),
+        };
+        if n == 0 || count == Some(0) || !ret.board.search(&mut ret.perm, &mut used, 0) {
+            ret.exhaust();
+        } else {
+            ret.used = used;
+        }
+        ret
+    }
+
+    /// Returns the number of derangements left, or `None` if it does not fit in a `usize` and [`len`] would panic.
+    /// 
+    /// [`len`]: https://doc.rust-lang.org/std/iter/trait.ExactSizeIterator.html#method.len
+    pub fn remaining(&self) -> Option<usize> {
+        self.remaining.get()
+    }
+
+    /// Returns the indices into `items` of the next derangement, or an empty slice if there are no derangements left.
+    pub fn indices(&self) -> &[usize] {
+        &self.perm
+    }
+
+    /// Advances the iterator and returns the next derangement, like [`next`], but in a buffer owned by the iterator
+    /// instead of a newly allocated `Vec`.
+    /// 
+    /// [`next`]: https://doc.rust-lang.org/std/iter/trait.Iterator.html#tymethod.next
+    pub fn next_slice(&mut self) -> Option<&[&'a T]> {
+        let mut buffer = std::mem::take(&mut self.buffer);
+        let more = self.next_into(&mut buffer);
+        self.buffer = buffer;
+        if more {
+            Some(&self.buffer)
+        } else {
+            None
+        }
+    }
+
+    /// Advances the iterator and writes the next derangement into `perm`, replacing its contents.
+    /// 
+    /// Returns `false`, leaving `perm` unchanged, if there are no derangements left.
+    pub fn next_into(&mut self, perm: &mut Vec<&'a T>) -> bool {
+        if self.remaining.is_zero() {
+            return false;
+        }
+        let items = self.items;
+        perm.clear();
+        perm.extend(self.perm.iter().map(|i| &items[*i]));
+        self.step();
+        true
+    }
+
+    /// Updates the remaining count after moving forward by `by` derangements.
+    fn consume(&mut self, by: usize) {
+        self.remaining = self.remaining.consumed(by, || {
+            let count = self.board.completions(0, &vec![false; self.items.len()]);
+            Some(count? - self.board.rank(&self.perm)?)
+        });
+    }
+}
+
+impl<T> Ranked for Derangements<'_, T> {
+    fn left(&self) -> Remaining {
+        self.remaining
+    }
+
+    fn rank(&self) -> Option<u128> {
+        self.board.rank(&self.perm)
+    }
+
+    fn jump(&mut self, rank: u128, by: usize) {
+        self.board.unrank_into(&mut self.perm, &mut self.used, rank);
+        self.consume(by);
+    }
+
+    fn step(&mut self) {
+        let last = self.perm.pop().unwrap();
+        self.used[last] = false;
+        if self.remaining.is_one() || !self.board.search(&mut self.perm, &mut self.used, last + 1) {
+            self.exhaust();
+        } else {
+            self.consume(1);
+        }
+    }
+
+    fn exhaust(&mut self) {
+        self.perm.clear();
+        self.used.clear();
+        self.remaining = Remaining::zero();
+    }
+}
+
+impl<T> Clone for Derangements<'_, T> {
+    fn clone(&self) -> Self {
+        Derangements {
+            items: self.items,
+            board: self.board.clone(),
+            perm: self.perm.clone(),
+            used: self.used.clone(),
+            remaining: self.remaining,
+            buffer: Vec::new(),
+        }
+    }
+}
+
+impl<'a, T> Iterator for Derangements<'a, T> {
+    type Item = Vec<&'a T>;
+
+    fn next(&mut self) -> Option<Vec<&'a T>> {
+        if self.remaining.is_zero() {
+            return None;
+        }
+        let ret = self.perm.iter().map(|i| &self.items[*i]).collect();
+        self.step();
+        Some(ret)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.remaining.size_hint()
+    }
+
+    fn count(self) -> usize {
+        self.remaining.get().expect("more than usize::MAX derangements")
+    }
+
+    fn nth(&mut self, n: usize) -> Option<Vec<&'a T>> {
+        if self.skip_ahead(n) {
+            self.next()
+        } else {
+            None
+        }
+    }
+}
+
+impl<T> ExactSizeIterator for Derangements<'_, T> {}
+
+impl<T> FusedIterator for Derangements<'_, T> {}
+
+/// Returns the multinomial coefficient `(c_1 + ... + c_m)! / (c_1! ... c_m!)`, the number of distinct permutations of
+/// a multiset with the given number of copies of each item, or `None` if it does not fit in a `usize`.
+/// 
+/// # Examples
+/// 
+/// ```
+/// // the arrangements of "AABBC"
+/// assert_eq!(gen_combinations::permutations::multinomial(&[2, 2, 1]), Some(30));
+/// ```
+pub fn multinomial(counts: &[usize]) -> Option<usize> {
+    // choose the positions of each item in turn among the positions so far
+    let mut total = 0usize;
+    let mut ret = 1usize;
+    for &count in counts {
+        total = total.checked_add(count)?;
+        ret = ret.checked_mul(binomial(total, count)?)?;
+    }
+    Some(ret)
+}
+
+/// Returns the number of permutations of `k` out of `n` items, `n! / (n - k)!`, or `None` if it does not fit in a
+/// `usize`.
+/// 
+/// Note that this is 1 when `k` is 0, whereas an iterator over permutations of length 0 produces no values.
+/// 
+/// # Examples
+/// 
+/// ```
+/// assert_eq!(gen_combinations::permutations::count(5, 2), Some(20));
+/// assert_eq!(gen_combinations::permutations::count(5, 5), Some(120));
+/// ```
+pub fn count(n: usize, k: usize) -> Option<usize> {
+    narrow(wide_count(n, k)?)
+}
+
+/// Returns the lexicographic rank of a permutation, given as the distinct `indices` of its items out of `n` total.
+/// 
+/// The first permutation, `[0, 1, ..., k - 1]`, has rank 0. Returns `None` if `indices` are not distinct, if any index
+/// is not less than `n`, or if the rank does not fit in a `usize`.
+/// 
+/// # Examples
+/// 
+/// ```
+/// assert_eq!(gen_combinations::permutations::rank(3, &[1, 0, 2]), Some(2));
+/// assert_eq!(gen_combinations::permutations::rank(3, &[1, 1]), None);
+/// ```
+pub fn rank(n: usize, indices: &[usize]) -> Option<usize> {
+    combination_of(n, indices)?;
+    narrow(wide_lex_rank(n, indices)?)
+}
+
+/// Returns the indices of the permutation of `k` out of `n` items with the given lexicographic `rank`.
+/// 
+/// This is the inverse of [`rank`]. Returns `None` if there are not more than `rank` such permutations.
+/// 
+/// # Examples
+/// 
+/// ```
+/// assert_eq!(gen_combinations::permutations::unrank(3, 3, 2), Some(vec![1, 0, 2]));
+/// ```
+/// 
+/// [`rank`]: fn.rank.html
+pub fn unrank(n: usize, k: usize, rank: usize) -> Option<Vec<usize>> {
+    if k > n || wide_count(n, k).is_some_and(|count| rank as u128 >= count) {
+        return None;
+    }
+    let mut perm = Vec::new();
+    lex_unrank_into(&mut perm, n, k, rank as u128);
+    perm.truncate(k);
+    Some(perm)
+}
+
+/// Returns the rank in plain-changes order (see [`PlainChanges`]) of a permutation, given as the distinct `indices` of
+/// its items out of `n` total.
+/// 
+/// The first permutation, `[0, 1, ..., k - 1]`, has rank 0. Returns `None` if `indices` are not distinct, if any index
+/// is not less than `n`, or if the rank does not fit in a `usize`.
+/// 
+/// # Examples
+/// 
+/// ```
+/// assert_eq!(gen_combinations::permutations::plain_changes_rank(3, &[2, 0, 1]), Some(2));
+/// ```
+/// 
+/// [`PlainChanges`]: struct.PlainChanges.html
+pub fn plain_changes_rank(n: usize, indices: &[usize]) -> Option<usize> {
+    let combo = combination_of(n, indices)?;
+    let k = indices.len();
+    let pattern: Vec<usize> = indices.iter().map(|i| combo.binary_search(i).unwrap()).collect();
+    // every arrangement of each earlier combination comes first, and if there are too many of those to count then
+    // only the first combination has a rank that fits
+    let rank = match wide_rank(n, &combo)? {
+        0 => 0,
+        combo_rank => combo_rank.checked_mul(wide_count(k, k)?)?,
+    };
+    narrow(rank.checked_add(pattern_rank(&pattern)?)?)
+}
+
+/// Returns the indices of the permutation of `k` out of `n` items with the given rank in plain-changes order.
+/// 
+/// This is the inverse of [`plain_changes_rank`]. Returns `None` if there are not more than `rank` such permutations.
+/// 
+/// # Examples
+/// 
+/// ```
+/// assert_eq!(gen_combinations::permutations::plain_changes_unrank(3, 3, 2), Some(vec![2, 0, 1]));
+/// ```
+/// 
+/// [`plain_changes_rank`]: fn.plain_changes_rank.html
+pub fn plain_changes_unrank(n: usize, k: usize, rank: usize) -> Option<Vec<usize>> {
+    if k > n || wide_count(n, k).is_some_and(|count| rank as u128 >= count) {
+        return None;
+    }
+    Some(plain_unrank_state(n, k, rank as u128).0)
+}
+
+/// Returns the subfactorial `!n`, the number of derangements of `n` items, or `None` if it does not fit in a `usize`.
+/// 
+/// Note that `!0` is 1, whereas a [`Derangements`] of no items produces no values.
+/// 
+/// # Examples
+/// 
+/// ```
+/// assert_eq!(gen_combinations::permutations::subfactorial(4), Some(9));
+/// assert_eq!(gen_combinations::permutations::subfactorial(1), Some(0));
+/// ```
+/// 
+/// [`Derangements`]: struct.Derangements.html
+pub fn subfactorial(n: usize) -> Option<usize> {
+    // !m = m * !(m - 1) + (-1)^m
+    (1..=n).try_fold(1usize, |count, m| {
+        let count = count.checked_mul(m)?;
+        if m % 2 == 0 {
+            count.checked_add(1)
+        } else {
+            Some(count - 1)
+        }
+    })
+}
+
+/// Returns `n! / (n - k)!` as a `u128`, or `None` if it does not fit.
+fn wide_count(n: usize, k: usize) -> Option<u128> {
+    if k > n {
+        return Some(0);
+    }
+    (n - k + 1..=n).try_fold(1u128, |count, i| count.checked_mul(i as u128))
+}
+
+/// Returns the indices in increasing order, or `None` if they are not distinct or any of them is not less than `n`.
+fn combination_of(n: usize, indices: &[usize]) -> Option<Vec<usize>> {
+    let mut combo = indices.to_vec();
+    combo.sort_unstable();
+    if combo.windows(2).any(|w| w[0] == w[1]) || combo.last().is_some_and(|&i| i >= n) {
+        return None;
+    }
+    Some(combo)
+}
+
+/// Returns the lexicographic rank of the distinct `indices` out of `n`, or `None` if it does not fit in a `u128`.
+fn wide_lex_rank(n: usize, indices: &[usize]) -> Option<u128> {
+    let k = indices.len();
+    let mut rank = 0u128;
+    for (i, &c) in indices.iter().enumerate() {
+        // every permutation that agrees up to position i but has a smaller unused item there comes first
+        let smaller = c - indices[..i].iter().filter(|&&x| x < c).count();
+        if smaller > 0 {
+            rank = rank.checked_add((smaller as u128).checked_mul(wide_count(n - 1 - i, k - 1 - i)?)?)?;
+        }
+    }
+    Some(rank)
+}
+
+/// Fills `perm` with the permutation of `k` out of `n` items with the given lexicographic rank, followed by the unused
+/// indices in increasing order.
+/// 
+/// `rank` must be less than the number of such permutations.
+fn lex_unrank_into(perm: &mut Vec<usize>, n: usize, k: usize, mut rank: u128) {
+    let mut unused: Vec<usize> = (0..n).collect();
+    perm.clear();
+    for i in 0..k {
+        // if there are too many permutations after this position to count, the rank is among those that use the
+        // smallest unused item
+        let d = match wide_count(n - 1 - i, k - 1 - i) {
+            Some(after) => {
+                let d = rank / after;
+                rank -= d * after;
+                d as usize
+            }
+            None => 0,
+        };
+        perm.push(unused.remove(d));
+    }
+    perm.extend(unused);
+}
+
+/// Moves `perm` to the next permutation in lexicographic order.
+/// 
+/// Returns `false`, leaving `perm` unchanged, if it is already the last permutation.
+fn next_permutation(perm: &mut [usize]) -> bool {
+    let i = match (1..perm.len()).rev().find(|&i| perm[i - 1] < perm[i]) {
+        Some(i) => i - 1,
+        None => return false,
+    };
+    let j = (i + 1..perm.len()).rev().find(|&j| perm[j] > perm[i]).unwrap();
+    perm.swap(i, j);
+    perm[i + 1..].reverse();
+    true
+}
+
+/// Returns the rank of `pattern`, a permutation of `0..pattern.len()`, in plain-changes order, or `None` if it does not
+/// fit in a `u128`.
+fn pattern_rank(pattern: &[usize]) -> Option<u128> {
+    // the permutations of 0..m come in blocks, one for each permutation of 0..m - 1, in which m - 1 sweeps from the
+    // end to the start or, in every other block, back again
+    let mut rank = 0u128;
+    for m in 2..=pattern.len() {
+        let at = pattern.iter().position(|&p| p == m - 1).unwrap();
+        let before = pattern[..at].iter().filter(|&&p| p < m - 1).count();
+        let step = if rank % 2 == 0 { m - 1 - before } else { before };
+        rank = rank.checked_mul(m as u128)?.checked_add(step as u128)?;
+    }
+    Some(rank)
+}
+
+/// Returns the permutation of `k` out of `n` items with the given rank in plain-changes order, along with its items in
+/// increasing order and its Algorithm P counters.
+/// 
+/// `rank` must be less than the number of such permutations.
+#[allow(clippy::type_complexity)]
+fn plain_unrank_state(n: usize, k: usize, rank: u128) -> (Vec<usize>, Vec<usize>, Vec<(usize, bool)>) {
+    // if there are too many arrangements of each combination to count, any rank is among those of the first one
+    let (combo_rank, mut rank) = match wide_count(k, k) {
+        Some(arrangements) => (rank / arrangements, rank % arrangements),
+        None => (0, rank),
+    };
+    let mut combo = vec![0; k];
+    unrank_into(&mut combo, 0, n, combo_rank);
+    // undo the blocks from the largest item down, then insert the items from the smallest up
+    let mut steps = vec![0; k];
+    for m in (2..=k).rev() {
+        steps[m - 1] = (rank % m as u128) as usize;
+        rank /= m as u128;
+    }
+    let mut pattern = vec![0];
+    let mut counters = vec![(0, true); k];
+    let mut block = 0u128;
+    for m in 2..=k {
+        let step = steps[m - 1];
+        let forward = block % 2 == 0;
+        pattern.insert(if forward { m - 1 - step } else { step }, m - 1);
+        counters[m - 1] = if forward { (step, true) } else { (m - 1 - step, false) };
+        block = block * m as u128 + step as u128;
+    }
+    pattern.truncate(k);
+    (pattern.iter().map(|&p| combo[p]).collect(), combo, counters)
+}
+
+/// Moves `perm` to the next arrangement of its items in plain-changes order, using Knuth's Algorithm P.
+/// 
+/// Returns the position `i` such that the items at `i` and `i + 1` were swapped, or `None`, leaving `perm` unchanged,
+/// if it was the last arrangement.
+fn plain_change(perm: &mut [usize], counters: &mut [(usize, bool)]) -> Option<usize> {
+    // with Knuth's 1-based j, where c_j is at counters[j - 1]
+    let mut s = 0;
+    for j in (1..=perm.len()).rev() {
+        let (c, up) = counters[j - 1];
+        let q = if up { Some(c + 1) } else { c.checked_sub(1) };
+        match q {
+            Some(q) if q == j => {
+                if j == 1 {
+                    return None;
+                }
+                s += 1;
+            }
+            Some(q) => {
+                let (x, y) = (j - c + s - 1, j - q + s - 1);
+                perm.swap(x, y);
+                counters[j - 1].0 = q;
+                return Some(x.min(y));
+            }
+            None => {}
+        }
+        counters[j - 1].1 = !up;
+    }
+    None
+}
+
+/// The positions ruled out for each index of a derangement, with the counts needed to rank by them.
+#[derive(Clone, Debug)]
+struct Board {
+    forbidden: Vec<(usize, usize)>, // sorted (position, index) pairs besides the diagonal
+    partial: Vec<Vec<u128>>, // partial[m][f]: arrangements of m indices with f of them kept out of their own positions
+}
+
+impl Board {
+    fn new(n: usize, forbidden: &[(usize, usize)]) -> Board {
+        let mut cells: Vec<(usize, usize)> =
+            forbidden.iter().copied().filter(|&(i, j)| i < n && j < n && i != j).collect();
+        cells.sort_unstable();
+        cells.dedup();
+        // stop at the first m whose m! does not fit, since every count for it is at least !m
+        let mut partial: Vec<Vec<u128>> = Vec::new();
+        let mut factorial = 1u128;
+        for m in 0..=n {
+            if m > 0 {
+                match factorial.checked_mul(m as u128) {
+                    Some(next) => factorial = next,
+                    None => break,
+                }
+            }
+            let mut row = vec![factorial];
+            for f in 1..=m {
+                row.push(row[f - 1] - partial[m - 1][f - 1]);
+            }
+            partial.push(row);
+        }
+        Board { forbidden: cells, partial }
+    }
+
+    fn allowed(&self, position: usize, index: usize) -> bool {
+        position != index && self.forbidden.binary_search(&(position, index)).is_err()
+    }
+
+    /// Returns the number of ways to fill the positions from `start` on with the unused indices, or `None` if it does
+    /// not fit in a `u128`.
+    fn completions(&self, start: usize, used: &[bool]) -> Option<u128> {
+        let n = used.len();
+        let m = n - start;
+        let cells: Vec<(usize, usize)> =
+            self.forbidden.iter().copied().filter(|&(i, j)| i >= start && !used[j]).collect();
+        let open: Vec<usize> = (start..n).filter(|&i| !used[i]).collect(); // the diagonal cells still open
+        if cells.is_empty() {
+            return self.partial.get(m).map(|row| row[open.len()]);
+        }
+        // inclusion–exclusion over the sets of non-attacking rooks on the forbidden cells still open, where the rows
+        // and columns sharing forbidden cells fall into groups whose rooks are placed independently of each other,
+        // and the diagonal cells alone in their row and column are left to `partial`
+        let mut parent: Vec<usize> = (0..2 * n).collect(); // position i is node i and index j is node n + j
+        for &(i, j) in &cells {
+            let (a, b) = (root(&mut parent, i), root(&mut parent, n + j));
+            parent[a] = b;
+        }
+        let mut groups: BTreeMap<usize, Vec<(usize, usize)>> = BTreeMap::new();
+        for &(i, j) in &cells {
+            groups.entry(root(&mut parent, i)).or_default().push((i, j));
+        }
+        let mut alone = 0;
+        for &i in &open {
+            let (a, b) = (root(&mut parent, i), root(&mut parent, n + i));
+            match (groups.contains_key(&a), groups.contains_key(&b)) {
+                (false, false) => alone += 1,
+                (true, true) if a != b => {
+                    // the diagonal cell joins the two groups of its row and column
+                    let mut cells = groups.remove(&a).unwrap();
+                    cells.push((i, i));
+                    groups.get_mut(&b).unwrap().append(&mut cells);
+                    parent[a] = b;
+                }
+                (true, _) => groups.get_mut(&a).unwrap().push((i, i)),
+                (false, true) => groups.get_mut(&b).unwrap().push((i, i)),
+            }
+        }
+        let mut rooks = vec![Natural::new(1)];
+        for (_, mut cells) in groups {
+            rooks = product(&rooks, &rook_counts(&mut cells));
+        }
+        let (mut added, mut taken) = (Natural::new(0), Natural::new(0));
+        for (s, ways) in rooks.iter().enumerate() {
+            let term = ways.times(&self.partial_count(m - s, alone));
+            if s % 2 == 0 {
+                added.add(&term);
+            } else {
+                taken.add(&term);
+            }
+        }
+        added.subtract(&taken);
+        added.narrow()
+    }
+
+    /// Returns the number of arrangements of `m` indices with `f` of them kept out of their own positions.
+    fn partial_count(&self, m: usize, f: usize) -> Natural {
+        if let Some(row) = self.partial.get(m) {
+            return Natural::new(row[f]);
+        }
+        // by inclusion–exclusion over the k of them left in their own positions, with C(f, k) (m - k)! ways each
+        let mut factorial = Natural::new(1);
+        for i in 2..=m {
+            factorial = factorial.times(&Natural::new(i as u128));
+        }
+        let (mut added, mut taken, mut ways) = (Natural::new(0), Natural::new(0), Natural::new(1));
+        for k in 0..=f {
+            let term = ways.times(&factorial);
+            if k % 2 == 0 {
+                added.add(&term);
+            } else {
+                taken.add(&term);
+            }
+            ways = ways.times(&Natural::new((f - k) as u128));
+            ways.divide(k as u64 + 1);
+            if k < m {
+                factorial.divide((m - k) as u64);
+            }
+        }
+        added.subtract(&taken);
+        added
+    }
+
+    /// Extends `perm` to the first derangement in lexicographic order whose next position holds at least `from`,
+    /// backtracking as needed, or returns `false` if there is none.
+    fn search(&self, perm: &mut Vec<usize>, used: &mut [bool], mut from: usize) -> bool {
+        let n = used.len();
+        while perm.len() < n {
+            let position = perm.len();
+            let mut next = None;
+            for index in from..n {
+                if !used[index] && self.allowed(position, index) {
+                    used[index] = true;
+                    if self.feasible(position + 1, used) {
+                        next = Some(index);
+                        break;
+                    }
+                    used[index] = false;
+                }
+            }
+            match next {
+                Some(index) => {
+                    perm.push(index);
+                    from = 0;
+                }
+                None => match perm.pop() {
+                    Some(index) => {
+                        used[index] = false;
+                        from = index + 1;
+                    }
+                    None => return false,
+                },
+            }
+        }
+        true
+    }
+
+    /// Returns whether the positions from `start` on can be filled with the unused indices at all, by looking for a
+    /// perfect matching between them, which takes polynomial time however many ways there are.
+    fn feasible(&self, start: usize, used: &[bool]) -> bool {
+        let n = used.len();
+        let free: Vec<usize> = (0..n).filter(|&i| !used[i]).collect();
+        let mut matched = vec![NIL; n]; // the index at each position from start on
+        let mut holder = vec![NIL; n]; // the position holding each index
+        // match greedily first, taking indices skipped at earlier positions before new ones
+        let (mut next, mut skipped) = (0, Vec::new());
+        for (position, slot) in matched.iter_mut().enumerate().skip(start) {
+            if let Some(s) = skipped.iter().position(|&i| self.allowed(position, i)) {
+                let index = skipped.remove(s);
+                *slot = index;
+                holder[index] = position;
+                continue;
+            }
+            while let Some(&index) = free.get(next) {
+                next += 1;
+                if self.allowed(position, index) {
+                    *slot = index;
+                    holder[index] = position;
+                    break;
+                }
+                skipped.push(index);
+            }
+        }
+        // then find an augmenting path for each position left over, and there is none if any of them has no path
+        let mut via = vec![NIL; n]; // the position each index was reached from
+        let unmatched: Vec<usize> = (start..n).filter(|&position| matched[position] == NIL).collect();
+        for first in unmatched {
+            via.iter_mut().for_each(|v| *v = NIL);
+            let mut queue = vec![first];
+            let mut end = None;
+            let mut q = 0;
+            while end.is_none() && q < queue.len() {
+                let position = queue[q];
+                q += 1;
+                for &index in &free {
+                    if via[index] == NIL && self.allowed(position, index) {
+                        via[index] = position;
+                        if holder[index] == NIL {
+                            end = Some(index);
+                            break;
+                        }
+                        queue.push(holder[index]);
+                    }
+                }
+            }
+            let mut index = match end {
+                Some(index) => index,
+                None => return false,
+            };
+            loop {
+                let position = via[index];
+                let displaced = matched[position];
+                matched[position] = index;
+                holder[index] = position;
+                if position == first {
+                    break;
+                }
+                index = displaced;
+            }
+        }
+        true
+    }
+
+    /// Returns the lexicographic rank of the derangement `perm`, or `None` if it does not fit in a `u128`.
+    fn rank(&self, perm: &[usize]) -> Option<u128> {
+        let mut used = vec![false; perm.len()];
+        let mut rank = 0u128;
+        for (position, &index) in perm.iter().enumerate() {
+            for smaller in 0..index {
+                if !used[smaller] && self.allowed(position, smaller) {
+                    used[smaller] = true;
+                    rank = rank.checked_add(self.completions(position + 1, &used)?)?;
+                    used[smaller] = false;
+                }
+            }
+            used[index] = true;
+        }
+        Some(rank)
+    }
+
+    /// Replaces `perm` with the derangement with the given lexicographic rank, which must exist.
+    fn unrank_into(&self, perm: &mut Vec<usize>, used: &mut [bool], mut rank: u128) {
+        let n = used.len();
+        perm.clear();
+        used.iter_mut().for_each(|u| *u = false);
+        for position in 0..n {
+            for index in 0..n {
+                if !used[index] && self.allowed(position, index) {
+                    used[index] = true;
+                    match self.completions(position + 1, used) {
+                        Some(count) if count <= rank => {
+                            rank -= count;
+                            used[index] = false;
+                        }
+                        _ => {
+                            perm.push(index);
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Returns the root of `x` in a union–find forest.
+fn root(parent: &mut [usize], mut x: usize) -> usize {
+    while parent[x] != x {
+        parent[x] = parent[parent[x]];
+        x = parent[x];
+    }
+    x
+}
+
+/// Returns the number of ways to place each number of non-attacking rooks on `cells`.
+/// 
+/// The rows are filled in turn, keeping apart the ways for each set of columns taken that later rows still have
+/// cells in, so the work grows with how many columns span the rows at once rather than with the number of cells.
+fn rook_counts(cells: &mut [(usize, usize)]) -> Vec<Natural> {
+    cells.sort_unstable();
+    let mut last = BTreeMap::new(); // the last row with a cell in each column
+    for &(i, j) in cells.iter() {
+        last.insert(j, i);
+    }
+    let mut ways: BTreeMap<Vec<usize>, Vec<Natural>> = once((Vec::new(), vec![Natural::new(1)])).collect();
+    for row in cells.chunk_by(|a, b| a.0 == b.0) {
+        let i = row[0].0;
+        let mut next = BTreeMap::new();
+        for (taken, counts) in ways {
+            // leave the row empty, or put a rook on one of its cells in a column not taken yet
+            let mut choices = vec![(taken.clone(), 0)];
+            for &(_, j) in row.iter().filter(|(_, j)| !taken.contains(j)) {
+                let mut taken = taken.clone();
+                taken.push(j);
+                choices.push((taken, 1));
+            }
+            for (mut taken, rooks) in choices {
+                taken.retain(|j| last[j] > i);
+                taken.sort_unstable();
+                let sum: &mut Vec<Natural> = next.entry(taken).or_default();
+                if sum.len() < counts.len() + rooks {
+                    sum.resize(counts.len() + rooks, Natural::new(0));
+                }
+                for (s, count) in counts.iter().enumerate() {
+                    sum[s + rooks].add(count);
+                }
+            }
+        }
+        ways = next;
+    }
+    ways.remove(&Vec::new()).unwrap_or_default()
+}
+
+/// Returns the product of two polynomials given by their coefficients.
+fn product(a: &[Natural], b: &[Natural]) -> Vec<Natural> {
+    let mut ret = vec![Natural::new(0); a.len() + b.len() - 1];
+    for (i, x) in a.iter().enumerate() {
+        for (j, y) in b.iter().enumerate() {
+            ret[i + j].add(&x.times(y));
+        }
+    }
+    ret
+}
+
+#[test]
+fn lexicographic_permutations() {
+    let items = [0, 1, 2, 3, 4];
+    for k in 1..=5 {
+        let perms: Vec<Vec<usize>> = Permutations::new(&items, k).map(|p| p.into_iter().copied().collect()).collect();
+        assert_eq!(perms.len(), count(5, k).unwrap());
+        let mut sorted = perms.clone();
+        sorted.sort();
+        sorted.dedup();
+        assert_eq!(sorted, perms);
+        for (r, perm) in perms.iter().enumerate() {
+            assert_eq!(rank(5, perm), Some(r));
+            assert_eq!(unrank(5, k, r).as_ref(), Some(perm));
+            let mut from = Permutations::from_rank(&items, k, r);
+            assert_eq!(from.len(), perms.len() - r);
+            assert_eq!(from.indices(), &perm[..]);
+            let nth: Option<Vec<usize>> = Permutations::new(&items, k).nth(r).map(|p| p.into_iter().copied().collect());
+            assert_eq!(nth.as_ref(), Some(perm));
+            assert_eq!(from.next_slice().map(|p| p.len()), Some(k));
+        }
+        assert_eq!(unrank(5, k, perms.len()), None);
+    }
+    assert_eq!(Permutations::new(&items, 0).next(), None);
+    assert_eq!(Permutations::new(&items, 6).next(), None);
+    assert_eq!(rank(5, &[0, 5]), None);
+}
+
+#[test]
+fn plain_changes() {
+    let items = [0, 1, 2, 3, 4];
+    for k in 1..=5 {
+        let mut perms = PlainChanges::new(&items, k);
+        assert_eq!(perms.len(), count(5, k).unwrap());
+        let mut seen = Vec::new();
+        let mut previous: Option<Vec<usize>> = None;
+        let mut r = 0;
+        while let Some(swap) = Some(perms.swapped()).filter(|_| perms.len() > 0) {
+            let perm: Vec<usize> = perms.next().unwrap().into_iter().copied().collect();
+            if let Some(i) = swap {
+                let mut expected = previous.clone().unwrap();
+                expected.swap(i, i + 1);
+                assert_eq!(perm, expected);
+            } else if let Some(previous) = &previous {
+                let mut before = previous.clone();
+                let mut after = perm.clone();
+                before.sort();
+                after.sort();
+                assert!(before < after);
+            }
+            assert_eq!(plain_changes_rank(5, &perm), Some(r));
+            assert_eq!(plain_changes_unrank(5, k, r).as_ref(), Some(&perm));
+            let mut skipped = PlainChanges::new(&items, k);
+            assert_eq!(skipped.nth(r).map(|p| p.into_iter().copied().collect()).as_ref(), Some(&perm));
+            assert_eq!(skipped.swapped(), perms.swapped());
+            assert_eq!(skipped.next(), perms.clone().next());
+            seen.push(perm.clone());
+            previous = Some(perm);
+            r += 1;
+        }
+        seen.sort();
+        seen.dedup();
+        assert_eq!(seen.len(), count(5, k).unwrap());
+    }
+
+    // with too many arrangements of each combination to count, only the first combination has ranks that fit
+    let first: Vec<usize> = (0..35).collect();
+    assert_eq!(plain_changes_rank(36, &first), Some(0));
+    assert_eq!(plain_changes_unrank(36, 35, 0), Some(first));
+    let mut later: Vec<usize> = (0..34).collect();
+    later.push(35);
+    assert_eq!(plain_changes_rank(36, &later), None);
+}
+
+#[test]
+fn plain_changes_order() {
+    let items = [1, 2, 3];
+    let mut perms = PlainChanges::new(&items, 3);
+    let mut order = Vec::new();
+    while let Some(perm) = perms.next_slice() {
+        order.push(perm.iter().copied().copied().collect::<Vec<_>>());
+    }
+    assert_eq!(order, [[1, 2, 3], [1, 3, 2], [3, 1, 2], [3, 2, 1], [2, 3, 1], [2, 1, 3]]);
+}
+
+#[test]
+fn multiset_permutations() {
+    let letters = ['A', 'B', 'A', 'C', 'B'];
+    let perms: Vec<String> = MultisetPermutations::new(&letters).map(|p| p.into_iter().collect()).collect();
+    let mut expected: Vec<String> = Permutations::new(&letters, 5).map(|p| p.into_iter().collect()).collect();
+    expected.sort();
+    expected.dedup();
+    let mut sorted = perms.clone();
+    sorted.sort();
+    assert_eq!(sorted, expected);
+    assert_eq!(perms.len(), multinomial(&[2, 2, 1]).unwrap());
+    assert_eq!(MultisetPermutations::new(&letters).len(), 30);
+
+    let mut perms = MultisetPermutations::from_counts(&[('x', 1), ('y', 0), ('z', 2)]);
+    assert_eq!(perms.next_slice(), Some(&[&'z', &'z', &'x'][..]));
+    assert_eq!(perms.len(), 2);
+    assert_eq!(perms.count(), 2);
+    assert_eq!(MultisetPermutations::new(&['q']).collect::<Vec<_>>(), [[&'q']]);
+    assert_eq!(MultisetPermutations::new(&[] as &[char]).next(), None);
+    assert_eq!(MultisetPermutations::from_counts(&[('a', 3)]).collect::<Vec<_>>(), [[&'a'; 3]]);
+    assert_eq!(MultisetPermutations::new_by_key(&[1, 2, 3], |x| x % 2).count(), 3);
+    assert_eq!(multinomial(&[40, 40, 40]), None);
+}
+
+#[test]
+fn derangements() {
+    let items = [0, 1, 2, 3, 4, 5];
+    let boards: [&[(usize, usize)]; 4] =
+        [&[], &[(0, 1), (1, 0)], &[(0, 1), (0, 2), (3, 3), (5, 4), (9, 0)], &[(2, 0), (2, 1), (2, 3)]];
+    for n in 0..=6 {
+        for forbidden in &boards {
+            let expected: Vec<Vec<usize>> = Permutations::new(&items[..n], n)
+                .map(|p| p.into_iter().copied().collect::<Vec<usize>>())
+                .filter(|p| p.iter().enumerate().all(|(i, &j)| i != j && !forbidden.contains(&(i, j))))
+                .collect();
+            let perms = Derangements::with_forbidden(&items[..n], forbidden);
+            assert_eq!(perms.len(), expected.len());
+            if forbidden.is_empty() && n > 0 {
+                assert_eq!(subfactorial(n), Some(expected.len()));
+            }
+            let found: Vec<Vec<usize>> = perms.map(|p| p.into_iter().copied().collect()).collect();
+            assert_eq!(found, expected);
+            for (r, perm) in expected.iter().enumerate() {
+                let mut skipped = Derangements::with_forbidden(&items[..n], forbidden);
+                assert_eq!(skipped.nth(r).map(|p| p.into_iter().copied().collect()).as_ref(), Some(perm));
+                assert_eq!(skipped.len(), expected.len() - r - 1);
+            }
+        }
+    }
+    assert_eq!(subfactorial(0), Some(1));
+    assert_eq!(subfactorial(20), Some(895_014_631_192_902_121));
+    assert_eq!(subfactorial(21), None);
+}
+
+#[test]
+fn large_derangements() {
+    let items: Vec<usize> = (0..1000).collect();
+    let mut perms = Derangements::new(&items);
+    assert_eq!(perms.size_hint(), (usize::MAX, None));
+    let first: Vec<usize> = perms.next_slice().unwrap().iter().map(|&&i| i).collect();
+    assert_eq!(&first[..4], [1, 0, 3, 2]);
+    assert_eq!(&first[996..], [997, 996, 999, 998]);
+    let second = perms.next().unwrap();
+    assert_eq!(&second[996..], [&997, &998, &999, &996]);
+
+    // no derangement can leave item 2 out of both positions 0 and 1 when there are three items
+    let mut none = Derangements::with_forbidden(&items[..3], &[(0, 2), (1, 2)]);
+    assert_eq!(none.len(), 0);
+    assert_eq!(none.next(), None);
+    let mut single = Derangements::new(&items[..2]);
+    assert_eq!(single.next_slice(), Some(&[&1, &0][..]));
+    assert_eq!(single.next(), None);
+
+    // item 39 has nowhere to go, which counting finds without trying the arrangements of the other items
+    let forbidden: Vec<(usize, usize)> = (0..40).map(|p| (p, 39)).collect();
+    let mut none = Derangements::with_forbidden(&items[..40], &forbidden);
+    assert_eq!(none.len(), 0);
+    assert_eq!(none.next(), None);
+
+    // a secret-santa draw where the partners in each of 10 couples may not draw each other
+    let forbidden: Vec<(usize, usize)> = (0..20).map(|p| (p, p ^ 1)).collect();
+    let mut some = Derangements::with_forbidden(&items[..20], &forbidden);
+    let count = 312_426_715_251_262_464;
+    assert_eq!((some.remaining(), some.size_hint()), (Some(count), (count, Some(count))));
+    let ok = |perm: &[usize]| perm.iter().enumerate().all(|(p, &i)| i != p && i != p ^ 1);
+    let first = some.indices().to_vec();
+    assert!(ok(&first));
+    let third: Vec<usize> = some.clone().nth(2).unwrap().into_iter().copied().collect();
+    some.next();
+    let second = some.indices().to_vec();
+    assert!(first < second && ok(&second));
+    some.next();
+    assert_eq!(some.indices(), &third[..]);
+    let last: Vec<usize> = some.clone().nth(count - 3).unwrap().into_iter().copied().collect();
+    assert_eq!(last, [19, 18, 17, 16, 15, 14, 13, 12, 11, 10, 9, 8, 7, 6, 5, 4, 3, 2, 1, 0]);
+    assert_eq!(some.len(), count - 2);
+}
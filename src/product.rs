@@ -0,0 +1,424 @@
+use crate::narrow;
+use crate::remaining::{Ranked, Remaining};
+use std::iter::FusedIterator;
+
+/// An order in which to produce the tuples of a [`Product`].
+/// 
+/// In both orders the last coordinate changes fastest, like the digits of a number counting up, where coordinate `i`
+/// is a digit in base `slices[i].len()`.
+/// 
+/// [`Product`]: struct.Product.html
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum ProductOrder {
+    /// Tuples are ordered by their first coordinate, then by their second, and so on. This is the default.
+    #[default]
+    Lexicographic,
+    /// Tuples are in reflected mixed-radix Gray order, where each tuple differs from the previous one in exactly one
+    /// coordinate, by one place. Each coordinate runs forward through its slice and then backward again, reversing
+    /// whenever the coordinates before it change.
+    Gray,
+}
+
+impl ProductOrder {
+    /// Returns the rank in this order of a tuple, given as the index of its item in each slice, where slice `i` has
+    /// `lens[i]` items.
+    /// 
+    /// The first tuple, `[0, 0, ..., 0]`, has rank 0. Returns `None` if `indices` does not have one index for each
+    /// slice, if any index is past the end of its slice, or if the rank does not fit in a `usize`.
+    /// 
+    /// # Examples
+    /// 
+    /// ```
+    /// use gen_combinations::ProductOrder;
+    /// 
+    /// assert_eq!(ProductOrder::Lexicographic.rank(&[2, 3], &[1, 0]), Some(3));
+    /// assert_eq!(ProductOrder::Gray.rank(&[2, 3], &[1, 0]), Some(5));
+    /// ```
+    pub fn rank(self, lens: &[usize], indices: &[usize]) -> Option<usize> {
+        if indices.len() != lens.len() || indices.iter().zip(lens).any(|(i, len)| i >= len) {
+            return None;
+        }
+        narrow(wide_rank(self, lens, indices)?)
+    }
+
+    /// Returns the indices of the tuple with the given `rank` in this order, where slice `i` has `lens[i]` items.
+    /// 
+    /// This is the inverse of [`rank`]. Returns `None` if there are not more than `rank` such tuples.
+    /// 
+    /// # Examples
+    /// 
+    /// ```
+    /// use gen_combinations::ProductOrder;
+    /// 
+    /// assert_eq!(ProductOrder::Gray.unrank(&[2, 3], 5), Some(vec![1, 0]));
+    /// ```
+    /// 
+    /// [`rank`]: #method.rank
+    pub fn unrank(self, lens: &[usize], rank: usize) -> Option<Vec<usize>> {
+        if lens.is_empty() || wide_count(lens).is_some_and(|count| rank as u128 >= count) {
+            return None;
+        }
+        let mut indices = vec![0; lens.len()];
+        unrank_into(self, lens, &mut indices, rank as u128);
+        Some(indices)
+    }
+}
+
+/// Iterates over the Cartesian product of several slices, producing one item from each.
+/// 
+/// The tuples are produced in the given [`ProductOrder`], lexicographic by default. Along with each tuple, the
+/// iterator reports which coordinate [`changed`] to reach the next one, which in Gray order is the only one that did.
+/// 
+/// If there are no slices, or any slice is empty, the iterator will produce no values.
+/// 
+/// # Examples
+/// 
+/// ```
+/// use gen_combinations::Product;
+/// 
+/// let sizes = ["S", "L"];
+/// let colours = ["red", "green", "blue"];
+/// let slices: [&[&str]; 2] = [&sizes, &colours];
+/// let grid: Vec<_> = Product::new(&slices).collect();
+/// assert_eq!(grid.len(), 6);
+/// assert_eq!(grid[0], [&"S", &"red"]);
+/// assert_eq!(grid[5], [&"L", &"blue"]);
+/// ```
+/// 
+/// [`ProductOrder`]: enum.ProductOrder.html
+/// [`changed`]: #method.changed
+#[derive(Debug)]
+pub struct Product<'a, T> {
+    slices: &'a [&'a [T]],
+    order: ProductOrder,
+    front: Vec<usize>, // the next tuple
+    forward: Vec<bool>, // in Gray order, whether each coordinate is moving forward
+    changed: Option<usize>, // the coordinate that changed to reach the next tuple
+    remaining: Remaining,
+    buffer: Vec<&'a T>, // reused by next_slice
+}
+
+impl<'a, T> Product<'a, T> {
+    /// Creates an iterator over the product of `slices` in lexicographic order.
+    pub fn new(slices: &'a [&'a [T]]) -> Product<'a, T> {
+        Product::with_order(slices, ProductOrder::Lexicographic)
+    }
+
+    /// Creates an iterator over the product of `slices` in lexicographic order, starting at the tuple with the given
+    /// `rank` (see [`ProductOrder::rank`]).
+    /// 
+    /// If `rank` is past the last tuple, the iterator will produce no values.
+    /// 
+    /// [`ProductOrder::rank`]: enum.ProductOrder.html#method.rank
+    pub fn from_rank(slices: &'a [&'a [T]], rank: usize) -> Product<'a, T> {
+        let mut ret = Product::new(slices);
+        if rank > 0 {
+            ret.nth(rank - 1);
+        }
+        ret
+    }
+
+    /// Creates an iterator over the product of `slices` in the given order.
+    /// 
+    /// # Examples
+    /// 
+    /// ```
+    /// use gen_combinations::{Product, ProductOrder};
+    /// 
+    /// let bits: [&[u8]; 3] = [&[0, 1], &[0, 1], &[0, 1]];
+    /// let mut gray = Product::with_order(&bits, ProductOrder::Gray);
+    /// let mut changes = Vec::new();
+    /// while gray.next_slice().is_some() {
+    ///     changes.extend(gray.changed());
+    /// }
+    /// assert_eq!(changes, [2, 1, 2, 0, 2, 1, 2]);
+    /// ```
+    pub fn with_order(slices: &'a [&'a [T]], order: ProductOrder) -> Product<'a, T> {
+        let lens: Vec<usize> = slices.iter().map(|slice| slice.len()).collect();
+        let remaining = if lens.is_empty() { Remaining::zero() } else { Remaining::new(wide_count(&lens)) };
+        Product {
+            slices,
+            order,
+            front: if remaining.is_zero() { Vec::new() } else { vec![0; slices.len()] },
+            forward: vec![true; slices.len()],
+            changed: None,
+            remaining,
+            buffer: Vec::new(),
+        }
+    }
+
+    /// Returns the order in which the tuples are produced.
+    pub fn order(&self) -> ProductOrder {
+        self.order
+    }
+
+    /// Returns the number of tuples left, or `None` if it does not fit in a `usize` and [`len`] would panic.
+    /// 
+    /// [`len`]: https://doc.rust-lang.org/std/iter/trait.ExactSizeIterator.html#method.len
+    pub fn remaining(&self) -> Option<usize> {
+        self.remaining.get()
+    }
+
+    /// Returns the index into each slice of the next tuple, or an empty slice if there are no tuples left.
+    pub fn indices(&self) -> &[usize] {
+        &self.front
+    }
+
+    /// Returns the coordinate that changed to reach the next tuple from the previous one, or `None` if the next tuple
+    /// is the first or there are none left.
+    /// 
+    /// In lexicographic order, the coordinates after it were also reset to the start of their slices.
+    pub fn changed(&self) -> Option<usize> {
+        self.changed
+    }
+
+    /// Advances the iterator and returns the next tuple, like [`next`], but in a buffer owned by the iterator instead
+    /// of a newly allocated `Vec`.
+    /// 
+    /// [`next`]: https://doc.rust-lang.org/std/iter/trait.Iterator.html#tymethod.next
+    pub fn next_slice(&mut self) -> Option<&[&'a T]> {
+        let mut buffer = std::mem::take(&mut self.buffer);
+        let more = self.next_into(&mut buffer);
+        self.buffer = buffer;
+        if more {
+            Some(&self.buffer)
+        } else {
+            None
+        }
+    }
+
+    /// Advances the iterator and writes the next tuple into `tuple`, replacing its contents.
+    /// 
+    /// Returns `false`, leaving `tuple` unchanged, if there are no tuples left.
+    pub fn next_into(&mut self, tuple: &mut Vec<&'a T>) -> bool {
+        if self.remaining.is_zero() {
+            return false;
+        }
+        let slices = self.slices;
+        tuple.clear();
+        tuple.extend(self.front.iter().zip(slices).map(|(&i, slice)| &slice[i]));
+        self.step();
+        true
+    }
+
+    /// Moves the front to the following tuple, returning the coordinate that was advanced.
+    fn advance(&mut self) -> Option<usize> {
+        for i in (0..self.front.len()).rev() {
+            let len = self.slices[i].len();
+            match self.order {
+                ProductOrder::Lexicographic => {
+                    if self.front[i] + 1 < len {
+                        self.front[i] += 1;
+                        return Some(i);
+                    }
+                    self.front[i] = 0;
+                }
+                ProductOrder::Gray => {
+                    if self.forward[i] && self.front[i] + 1 < len {
+                        self.front[i] += 1;
+                        return Some(i);
+                    } else if !self.forward[i] && self.front[i] > 0 {
+                        self.front[i] -= 1;
+                        return Some(i);
+                    }
+                    self.forward[i] = !self.forward[i];
+                }
+            }
+        }
+        None
+    }
+
+    fn count_remaining(&self) -> Option<u128> {
+        let lens = self.lens();
+        Some(wide_count(&lens)? - wide_rank(self.order, &lens, &self.front)?)
+    }
+
+    fn lens(&self) -> Vec<usize> {
+        self.slices.iter().map(|slice| slice.len()).collect()
+    }
+}
+
+impl<T> Ranked for Product<'_, T> {
+    fn left(&self) -> Remaining {
+        self.remaining
+    }
+
+    fn rank(&self) -> Option<u128> {
+        wide_rank(self.order, &self.lens(), &self.front)
+    }
+
+    fn jump(&mut self, rank: u128, by: usize) {
+        let lens = self.lens();
+        unrank_into(self.order, &lens, &mut self.front, rank);
+        self.forward = directions(self.order, &lens, &self.front);
+        self.remaining = self.remaining.consumed(by, || self.count_remaining());
+    }
+
+    fn step(&mut self) {
+        self.changed = if self.remaining.is_one() { None } else { self.advance() };
+        match self.changed {
+            Some(_) => self.remaining = self.remaining.consumed(1, || self.count_remaining()),
+            None => self.exhaust(),
+        }
+    }
+
+    fn exhaust(&mut self) {
+        self.front.clear();
+        self.changed = None;
+        self.remaining = Remaining::zero();
+    }
+}
+
+impl<T> Clone for Product<'_, T> {
+    fn clone(&self) -> Self {
+        Product {
+            slices: self.slices,
+            order: self.order,
+            front: self.front.clone(),
+            forward: self.forward.clone(),
+            changed: self.changed,
+            remaining: self.remaining,
+            buffer: Vec::new(),
+        }
+    }
+}
+
+impl<'a, T> Iterator for Product<'a, T> {
+    type Item = Vec<&'a T>;
+
+    fn next(&mut self) -> Option<Vec<&'a T>> {
+        if self.remaining.is_zero() {
+            return None;
+        }
+        let ret = self.front.iter().zip(self.slices).map(|(&i, slice)| &slice[i]).collect();
+        self.step();
+        Some(ret)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.remaining.size_hint()
+    }
+
+    fn count(self) -> usize {
+        self.remaining.get().expect("more than usize::MAX tuples")
+    }
+
+    fn nth(&mut self, n: usize) -> Option<Vec<&'a T>> {
+        if self.skip_ahead(n) {
+            self.next()
+        } else {
+            None
+        }
+    }
+}
+
+impl<T> ExactSizeIterator for Product<'_, T> {}
+
+impl<T> FusedIterator for Product<'_, T> {}
+
+/// Returns the product of `lens`, or `None` if it does not fit in a `u128`.
+fn wide_count(lens: &[usize]) -> Option<u128> {
+    lens.iter().try_fold(1u128, |count, &len| count.checked_mul(len as u128))
+}
+
+/// Returns the rank of a tuple, or `None` if it does not fit in a `u128`.
+fn wide_rank(order: ProductOrder, lens: &[usize], indices: &[usize]) -> Option<u128> {
+    let mut rank = 0u128;
+    for (&i, &len) in indices.iter().zip(lens) {
+        // in Gray order, a coordinate runs backward whenever the rank of the coordinates before it is odd
+        let digit = if order == ProductOrder::Gray && rank % 2 == 1 { len - 1 - i } else { i };
+        rank = rank.checked_mul(len as u128)?.checked_add(digit as u128)?;
+    }
+    Some(rank)
+}
+
+/// Fills `indices` with the tuple with the given rank, which must be less than the number of tuples.
+fn unrank_into(order: ProductOrder, lens: &[usize], indices: &mut [usize], mut rank: u128) {
+    for (i, &len) in lens.iter().enumerate().rev() {
+        indices[i] = (rank % len as u128) as usize;
+        rank /= len as u128;
+    }
+    if order == ProductOrder::Gray {
+        let mut prefix_odd = false;
+        for (i, &len) in lens.iter().enumerate() {
+            let digit = indices[i];
+            if prefix_odd {
+                indices[i] = len - 1 - digit;
+            }
+            // the parity of prefix * len + digit
+            prefix_odd = (prefix_odd && len % 2 == 1) != (digit % 2 == 1);
+        }
+    }
+}
+
+/// Returns whether each coordinate of the tuple in `indices` is moving forward in the given order.
+fn directions(order: ProductOrder, lens: &[usize], indices: &[usize]) -> Vec<bool> {
+    let mut forward = vec![true; lens.len()];
+    if order == ProductOrder::Gray {
+        let mut prefix_odd = false;
+        for (i, &len) in lens.iter().enumerate() {
+            forward[i] = !prefix_odd;
+            let digit = if prefix_odd { len - 1 - indices[i] } else { indices[i] };
+            prefix_odd = (prefix_odd && len % 2 == 1) != (digit % 2 == 1);
+        }
+    }
+    forward
+}
+
+#[test]
+fn lexicographic_product() {
+    let a = [1, 2];
+    let b = [3];
+    let c = [4, 5, 6];
+    let slices: [&[i32]; 3] = [&a, &b, &c];
+    let tuples: Vec<Vec<i32>> = Product::new(&slices).map(|t| t.into_iter().copied().collect()).collect();
+    assert_eq!(tuples, [[1, 3, 4], [1, 3, 5], [1, 3, 6], [2, 3, 4], [2, 3, 5], [2, 3, 6]]);
+    for (r, expected) in tuples.iter().enumerate() {
+        let indices = ProductOrder::Lexicographic.unrank(&[2, 1, 3], r).unwrap();
+        assert_eq!(ProductOrder::Lexicographic.rank(&[2, 1, 3], &indices), Some(r));
+        let mut p = Product::from_rank(&slices, r);
+        assert_eq!(p.len(), 6 - r);
+        assert_eq!(p.next().map(|t| t.into_iter().copied().collect()).as_ref(), Some(expected));
+    }
+    let mut p = Product::new(&slices);
+    assert_eq!(p.nth(2), Some(vec![&1, &3, &6]));
+    assert_eq!(p.changed(), Some(0));
+    assert_eq!(p.indices(), &[1, 0, 0]);
+    assert_eq!(p.next_slice(), Some(&[&2, &3, &4][..]));
+
+    let empty: [&[i32]; 2] = [&a, &[]];
+    assert_eq!(Product::new(&empty).next(), None);
+    assert_eq!(Product::<i32>::new(&[]).next(), None);
+    assert_eq!(ProductOrder::Lexicographic.rank(&[2, 3], &[2, 0]), None);
+}
+
+#[test]
+fn gray_product() {
+    let lens = [3, 2, 4, 3];
+    let slices: Vec<Vec<usize>> = lens.iter().map(|&len| (0..len).collect()).collect();
+    let slices: Vec<&[usize]> = slices.iter().map(|slice| &slice[..]).collect();
+    let mut p = Product::with_order(&slices, ProductOrder::Gray);
+    let mut seen = Vec::new();
+    let mut previous: Option<Vec<usize>> = None;
+    while let Some(tuple) = p.next() {
+        let tuple: Vec<usize> = tuple.into_iter().copied().collect();
+        if let Some(previous) = previous {
+            let changed: Vec<usize> = (0..4).filter(|&i| previous[i] != tuple[i]).collect();
+            assert_eq!(changed.len(), 1);
+            assert_eq!(previous[changed[0]].abs_diff(tuple[changed[0]]), 1);
+        }
+        let r = seen.len();
+        assert_eq!(ProductOrder::Gray.rank(&lens, &tuple), Some(r));
+        assert_eq!(ProductOrder::Gray.unrank(&lens, r).as_ref(), Some(&tuple));
+        let mut skipped = Product::with_order(&slices, ProductOrder::Gray);
+        assert_eq!(skipped.nth(r).map(|t| t.into_iter().copied().collect()).as_ref(), Some(&tuple));
+        assert_eq!(skipped.changed(), p.changed());
+        assert_eq!(skipped.clone().collect::<Vec<_>>(), p.clone().collect::<Vec<_>>());
+        seen.push(tuple.clone());
+        previous = Some(tuple);
+    }
+    assert_eq!(seen.len(), 72);
+    seen.sort();
+    seen.dedup();
+    assert_eq!(seen.len(), 72);
+}
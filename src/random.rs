@@ -0,0 +1,108 @@
+use rand::Rng;
+use std::collections::BTreeSet;
+
+/// Returns a uniformly random combination of `k` out of `items`, or `None` if `k` is 0 or greater than `items.len()`.
+/// 
+/// Every combination that a [`CombinationIterator`] with the same arguments would produce is equally likely, and it
+/// comes in the same shape, with the items in their order in `items`. Nothing is enumerated, so this takes time in `k`
+/// alone however many combinations there are, and the same seeded `rng` always gives the same combination. It is only
+/// available with the `rand` feature.
+/// 
+/// # Examples
+/// 
+/// ```
+/// use rand::rngs::StdRng;
+/// use rand::SeedableRng;
+/// 
+/// let items: Vec<u32> = (0..1000).collect();
+/// let mut rng = StdRng::seed_from_u64(7);
+/// let combo = gen_combinations::random_combination(&items, 5, &mut rng).unwrap();
+/// assert_eq!(combo.len(), 5);
+/// assert!(combo.windows(2).all(|w| w[0] < w[1]));
+/// assert_eq!(Some(combo), gen_combinations::random_combination(&items, 5, &mut StdRng::seed_from_u64(7)));
+/// ```
+/// 
+/// [`CombinationIterator`]: struct.CombinationIterator.html
+pub fn random_combination<'a, T, R: Rng + ?Sized>(items: &'a [T], k: usize, rng: &mut R) -> Option<Vec<&'a T>> {
+    let indices = random_indices(items.len(), k, rng)?;
+    Some(indices.into_iter().map(|i| &items[i]).collect())
+}
+
+/// Returns the strictly increasing indices of a uniformly random combination of `k` out of `n` items, or `None` if `k`
+/// is 0 or greater than `n`.
+/// 
+/// This is [`random_combination`] for a universe of indices, like [`IndexCombinations`], so `n` may be far larger
+/// than any slice. It is only available with the `rand` feature.
+/// 
+/// # Examples
+/// 
+/// ```
+/// use rand::rngs::StdRng;
+/// use rand::SeedableRng;
+/// 
+/// let mut rng = StdRng::seed_from_u64(7);
+/// let indices = gen_combinations::random_indices(usize::MAX, 3, &mut rng).unwrap();
+/// assert!(indices.windows(2).all(|w| w[0] < w[1]));
+/// assert_eq!(gen_combinations::random_indices(3, 4, &mut rng), None);
+/// ```
+/// 
+/// [`random_combination`]: fn.random_combination.html
+/// [`IndexCombinations`]: struct.IndexCombinations.html
+pub fn random_indices<R: Rng + ?Sized>(n: usize, k: usize, rng: &mut R) -> Option<Vec<usize>> {
+    if k == 0 || k > n {
+        return None;
+    }
+    // Floyd's algorithm: after the step for `j`, the chosen indices are a uniform combination out of `0..=j`
+    let mut chosen = BTreeSet::new();
+    for j in n - k..n {
+        let t = rng.random_range(0..=j);
+        if !chosen.insert(t) {
+            chosen.insert(j);
+        }
+    }
+    Some(chosen.into_iter().collect())
+}
+
+#[test]
+fn random_combinations_are_uniform() {
+    use crate::{rank, CombinationIterator};
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    let items: Vec<usize> = (0..6).collect();
+    let all: Vec<_> = CombinationIterator::new(&items, 3).collect();
+    let mut rng = StdRng::seed_from_u64(2024);
+    let mut counts = vec![0usize; all.len()];
+    let samples = 40_000;
+    for _ in 0..samples {
+        let combo = random_combination(&items, 3, &mut rng).unwrap();
+        let indices: Vec<usize> = combo.iter().map(|&&i| i).collect();
+        counts[rank(items.len(), &indices).unwrap()] += 1;
+    }
+    // each of the 20 combinations is expected 2000 times, with a standard deviation of about 44
+    let expected = samples / all.len();
+    assert!(counts.iter().all(|&count| count.abs_diff(expected) < 250), "{:?}", counts);
+
+    assert_eq!(random_combination(&items, 6, &mut rng), Some(items.iter().collect()));
+    assert_eq!(random_combination(&items, 0, &mut rng), None);
+    assert_eq!(random_combination(&items, 7, &mut rng), None);
+}
+
+#[test]
+fn random_indices_are_reproducible() {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    let draw = |seed| {
+        let mut rng = StdRng::seed_from_u64(seed);
+        (0..10).map(|_| random_indices(usize::MAX / 3, 50, &mut rng).unwrap()).collect::<Vec<_>>()
+    };
+    let first = draw(1);
+    assert_eq!(first, draw(1));
+    assert_ne!(first, draw(2));
+    for indices in &first {
+        assert_eq!(indices.len(), 50);
+        assert!(indices.windows(2).all(|w| w[0] < w[1]));
+        assert!(indices.iter().all(|&i| i < usize::MAX / 3));
+    }
+}
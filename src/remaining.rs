@@ -0,0 +1,113 @@
+use crate::narrow;
+
+/// The number of values an iterator has left, kept as a `u128` so that stepping past a value only has to subtract,
+/// even when the count is too large for a `usize`.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Remaining {
+    count: Option<u128>, // None if it does not fit in a u128
+    uncounted: u128, // values consumed since the count last did not fit
+}
+
+impl Remaining {
+    pub(crate) fn new(count: Option<u128>) -> Remaining {
+        Remaining { count, uncounted: 0 }
+    }
+
+    pub(crate) fn zero() -> Remaining {
+        Remaining::new(Some(0))
+    }
+
+    /// Returns the count, or `None` if it does not fit in a `usize`.
+    pub(crate) fn get(&self) -> Option<usize> {
+        self.count.and_then(narrow)
+    }
+
+    pub(crate) fn is_zero(&self) -> bool {
+        self.count == Some(0)
+    }
+
+    pub(crate) fn is_one(&self) -> bool {
+        self.count == Some(1)
+    }
+
+    /// Returns whether more than `n` values are left.
+    pub(crate) fn exceeds(&self, n: usize) -> bool {
+        self.count.is_none_or(|count| count > n as u128)
+    }
+
+    pub(crate) fn size_hint(&self) -> (usize, Option<usize>) {
+        match self.get() {
+            Some(count) => (count, Some(count)),
+            None => (usize::MAX, None),
+        }
+    }
+
+    /// Returns the count after `by` more values are consumed.
+    /// 
+    /// A count too large for a `u128` is only recounted with `recount` once so many values have been consumed since
+    /// that it might fit in a `usize` again, rather than on every step.
+    pub(crate) fn consumed<F: FnOnce() -> Option<u128>>(self, by: usize, recount: F) -> Remaining {
+        match self.count {
+            Some(count) => Remaining::new(Some(count - by as u128)),
+            None => {
+                let uncounted = self.uncounted.saturating_add(by as u128);
+                if uncounted > u128::MAX - usize::MAX as u128 {
+                    Remaining::new(recount())
+                } else {
+                    Remaining { count: None, uncounted }
+                }
+            }
+        }
+    }
+}
+
+/// An iterator whose values have ranks, so that it can skip ahead by unranking rather than generating each value in
+/// turn.
+pub(crate) trait Ranked {
+    /// Returns the count of values left.
+    fn left(&self) -> Remaining;
+
+    /// Returns the rank of the next value, or `None` if it does not fit in a `u128`.
+    fn rank(&self) -> Option<u128>;
+
+    /// Moves to the value with the given rank, `by` values after the next one, which must exist.
+    fn jump(&mut self, rank: u128, by: usize);
+
+    /// Moves past the next value, which must exist.
+    fn step(&mut self);
+
+    /// Moves past every value left.
+    fn exhaust(&mut self);
+
+    /// Moves forward by `by` values without producing them.
+    /// 
+    /// Returns `false`, leaving no values, if there are not more than `by` values left.
+    fn skip_ahead(&mut self, by: usize) -> bool {
+        if !self.left().exceeds(by) {
+            self.exhaust();
+            return false;
+        }
+        if by > 0 {
+            match self.rank().and_then(|rank| rank.checked_add(by as u128)) {
+                Some(target) => self.jump(target, by),
+                // too far in to rank, so step instead
+                None => (0..by).for_each(|_| self.step()),
+            }
+        }
+        true
+    }
+}
+
+#[test]
+fn recounts_only_when_it_could_fit() {
+    let huge = Remaining::new(None).consumed(usize::MAX, || panic!("recounted"));
+    assert_eq!((huge.get(), huge.exceeds(usize::MAX), huge.size_hint()), (None, true, (usize::MAX, None)));
+    let near = Remaining { count: None, uncounted: u128::MAX - usize::MAX as u128 - 1 };
+    assert_eq!(near.consumed(1, || panic!("recounted")).get(), None);
+    assert_eq!(near.consumed(2, || Some(3)).get(), Some(3));
+
+    let wide = Remaining::new(Some(usize::MAX as u128 + 2)).consumed(1, || panic!("recounted"));
+    assert_eq!(wide.get(), None);
+    assert_eq!(wide.consumed(1, || None).get(), Some(usize::MAX));
+    assert!(wide.exceeds(usize::MAX));
+}
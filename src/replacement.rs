@@ -0,0 +1,250 @@
+use crate::cursor::Cursor;
+use crate::{binomial, rank, unrank, Order};
+use std::iter::FusedIterator;
+
+/// Iterates over all possible combinations of items with repetition, also known as multisets.
+/// 
+/// Each item may appear any number of times in a combination. Combinations are produced in lexicographic order of
+/// their indices into `items`, which are nondecreasing within each combination. There are
+/// [`count_with_replacement`]`(items.len(), n)` of them.
+/// 
+/// # Examples
+/// 
+/// ```
+/// use gen_combinations::CombinationsWithReplacement;
+/// 
+/// let items = [1, 2, 3];
+/// for combo in CombinationsWithReplacement::new(&items, 2) {
+///     println!("{:?}", combo);
+///     // [1, 1]
+///     // [1, 2]
+///     // [1, 3]
+///     // [2, 2]
+///     // [2, 3]
+///     // [3, 3]
+/// }
+/// ```
+/// 
+/// [`count_with_replacement`]: fn.count_with_replacement.html
+#[derive(Debug)]
+pub struct CombinationsWithReplacement<'a, T> {
+    items: &'a [T],
+    cursor: Cursor, // over n + k - 1 items, where each combination has i subtracted from its i-th index
+    buffer: Vec<&'a T>, // reused by next_slice
+}
+
+impl<'a, T> CombinationsWithReplacement<'a, T> {
+    /// Creates an iterator over combinations of `items` with repetition and length `n`.
+    /// 
+    /// If `n` is 0, `items` is empty, or `items.len() + n - 1` does not fit in a `usize`, the iterator will produce no
+    /// values.
+    pub fn new(items: &[T], n: usize) -> CombinationsWithReplacement<'_, T> {
+        let cursor = match spread(items.len(), n) {
+            Some(spread) => Cursor::new(spread, n),
+            None => Cursor::empty(0, n, Order::Lexicographic),
+        };
+        CombinationsWithReplacement { items, cursor, buffer: Vec::new() }
+    }
+
+    /// Creates an iterator over combinations of `items` with repetition and length `n`, starting at the combination
+    /// with the given lexicographic `rank` (see [`rank_with_replacement`]).
+    /// 
+    /// If `rank` is past the last combination, or `items.len() + n - 1` does not fit in a `usize`, the iterator will
+    /// produce no values.
+    /// 
+    /// [`rank_with_replacement`]: fn.rank_with_replacement.html
+    pub fn from_rank(items: &[T], n: usize, rank: usize) -> CombinationsWithReplacement<'_, T> {
+        let spread = match spread(items.len(), n) {
+            Some(spread) => spread,
+            None => return CombinationsWithReplacement::new(items, n),
+        };
+        let front = if n == 0 { Vec::new() } else { unrank(spread, n, rank).unwrap_or_default() };
+        let cursor = Cursor::starting_at(spread, front, Order::Lexicographic);
+        CombinationsWithReplacement { items, cursor, buffer: Vec::new() }
+    }
+
+    /// Returns the number of combinations left, or `None` if it does not fit in a `usize` and [`len`] would panic.
+    /// 
+    /// [`len`]: https://doc.rust-lang.org/std/iter/trait.ExactSizeIterator.html#method.len
+    pub fn remaining(&self) -> Option<usize> {
+        self.cursor.remaining()
+    }
+
+    /// Advances the iterator and returns the next combination, like [`next`], but in a buffer owned by the iterator
+    /// instead of a newly allocated `Vec`.
+    /// 
+    /// [`next`]: https://doc.rust-lang.org/std/iter/trait.Iterator.html#tymethod.next
+    pub fn next_slice(&mut self) -> Option<&[&'a T]> {
+        let (items, buffer) = (self.items, &mut self.buffer);
+        self.cursor.next_front(|spread| {
+            buffer.clear();
+            buffer.extend(spread.iter().enumerate().map(|(i, c)| &items[c - i]));
+        })?;
+        Some(&self.buffer)
+    }
+}
+
+impl<T> Clone for CombinationsWithReplacement<'_, T> {
+    fn clone(&self) -> Self {
+        CombinationsWithReplacement { items: self.items, cursor: self.cursor.clone(), buffer: Vec::new() }
+    }
+}
+
+impl<'a, T> Iterator for CombinationsWithReplacement<'a, T> {
+    type Item = Vec<&'a T>;
+
+    fn next(&mut self) -> Option<Vec<&'a T>> {
+        let items = self.items;
+        self.cursor.next_front(|spread| gather(items, spread))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.cursor.size_hint()
+    }
+
+    fn count(self) -> usize {
+        self.cursor.len()
+    }
+
+    fn nth(&mut self, n: usize) -> Option<Vec<&'a T>> {
+        let items = self.items;
+        self.cursor.nth_front(n, |spread| gather(items, spread))
+    }
+}
+
+impl<'a, T> DoubleEndedIterator for CombinationsWithReplacement<'a, T> {
+    fn next_back(&mut self) -> Option<Vec<&'a T>> {
+        let items = self.items;
+        self.cursor.next_back(|spread| gather(items, spread))
+    }
+
+    fn nth_back(&mut self, n: usize) -> Option<Vec<&'a T>> {
+        let items = self.items;
+        self.cursor.nth_back(n, |spread| gather(items, spread))
+    }
+}
+
+impl<T> ExactSizeIterator for CombinationsWithReplacement<'_, T> {}
+
+impl<T> FusedIterator for CombinationsWithReplacement<'_, T> {}
+
+/// Returns the number of combinations of `k` out of `n` items with repetition, `C(n + k - 1, k)`, or `None` if it
+/// does not fit in a `usize`.
+/// 
+/// # Examples
+/// 
+/// ```
+/// // 3 scoops from 5 flavours
+/// assert_eq!(gen_combinations::count_with_replacement(5, 3), Some(35));
+/// ```
+pub fn count_with_replacement(n: usize, k: usize) -> Option<usize> {
+    if n == 0 {
+        return Some(if k == 0 { 1 } else { 0 });
+    }
+    // n + k - 1 only overflows if n is at least 2, and then there are at least that many combinations
+    binomial(spread(n, k)?, k)
+}
+
+/// Returns the lexicographic rank of a combination with repetition, given as the nondecreasing `indices` of its items
+/// out of `n` total.
+/// 
+/// Returns `None` if `indices` is not nondecreasing, if any index is not less than `n`, if `n + indices.len() - 1` does
+/// not fit in a `usize`, or if the rank does not fit in a `usize`.
+/// 
+/// # Examples
+/// 
+/// ```
+/// assert_eq!(gen_combinations::rank_with_replacement(3, &[0, 0]), Some(0));
+/// assert_eq!(gen_combinations::rank_with_replacement(3, &[1, 1]), Some(3));
+/// ```
+pub fn rank_with_replacement(n: usize, indices: &[usize]) -> Option<usize> {
+    if indices.windows(2).any(|w| w[0] > w[1]) || indices.last().is_some_and(|&i| i >= n) {
+        return None;
+    }
+    let total = spread(n, indices.len())?;
+    let spread: Vec<usize> = indices.iter().enumerate().map(|(i, c)| c + i).collect();
+    rank(total, &spread)
+}
+
+/// Returns the indices of the combination with repetition of `k` out of `n` items with the given lexicographic `rank`.
+/// 
+/// This is the inverse of [`rank_with_replacement`]. Returns `None` if there are not more than `rank` such
+/// combinations, or if `n + k - 1` does not fit in a `usize`.
+/// 
+/// # Examples
+/// 
+/// ```
+/// assert_eq!(gen_combinations::unrank_with_replacement(3, 2, 3), Some(vec![1, 1]));
+/// ```
+/// 
+/// [`rank_with_replacement`]: fn.rank_with_replacement.html
+pub fn unrank_with_replacement(n: usize, k: usize, rank: usize) -> Option<Vec<usize>> {
+    if n == 0 {
+        return if k == 0 && rank == 0 { Some(Vec::new()) } else { None };
+    }
+    let mut indices = unrank(spread(n, k)?, k, rank)?;
+    for (i, c) in indices.iter_mut().enumerate() {
+        *c -= i;
+    }
+    Some(indices)
+}
+
+/// Returns `n + k - 1`, the number of items that combinations of `k` out of `n` with repetition are spread out over,
+/// or `None` if it does not fit in a `usize`.
+fn spread(n: usize, k: usize) -> Option<usize> {
+    match n.checked_sub(1) {
+        Some(m) => m.checked_add(k),
+        None => Some(k.saturating_sub(1)),
+    }
+}
+
+/// Returns the items picked by a combination of `spread.len()` out of `items.len() + spread.len() - 1` indices.
+fn gather<'a, T>(items: &'a [T], spread: &[usize]) -> Vec<&'a T> {
+    spread.iter().enumerate().map(|(i, c)| &items[c - i]).collect()
+}
+
+#[test]
+fn combinations_with_replacement() {
+    let items = [1, 2, 3, 4, 5];
+    let combos: Vec<Vec<&i32>> = CombinationsWithReplacement::new(&items, 3).collect();
+    assert_eq!(combos.len(), 35);
+    assert_eq!(combos.len(), count_with_replacement(5, 3).unwrap());
+    for (r, combo) in combos.iter().enumerate() {
+        assert!(combo.windows(2).all(|w| w[0] <= w[1]));
+        let indices: Vec<usize> = combo.iter().map(|&&x| x as usize - 1).collect();
+        assert_eq!(rank_with_replacement(5, &indices), Some(r));
+        assert_eq!(unrank_with_replacement(5, 3, r), Some(indices));
+        assert_eq!(CombinationsWithReplacement::from_rank(&items, 3, r).next().as_ref(), Some(combo));
+    }
+    let mut sorted = combos.clone();
+    sorted.sort();
+    sorted.dedup();
+    assert_eq!(sorted, combos);
+    assert_eq!(unrank_with_replacement(5, 3, 35), None);
+
+    let reversed: Vec<_> = CombinationsWithReplacement::new(&items, 3).rev().collect();
+    assert_eq!(reversed, combos.iter().rev().cloned().collect::<Vec<_>>());
+    let mut c = CombinationsWithReplacement::new(&items, 3);
+    assert_eq!(c.nth(20).as_ref(), Some(&combos[20]));
+    assert_eq!(c.next_slice(), Some(&combos[21][..]));
+    assert_eq!(c.len(), 13);
+}
+
+#[test]
+fn misuse_replacement_arguments() {
+    let items = [1, 2, 3];
+    assert_eq!(CombinationsWithReplacement::new(&items, 0).next(), None);
+    assert_eq!(CombinationsWithReplacement::new(&[] as &[i32], 2).next(), None);
+    assert_eq!(CombinationsWithReplacement::new(&items, 5).count(), 21);
+    assert_eq!(CombinationsWithReplacement::new(&items, 5).next_back(), Some(vec![&3; 5]));
+    assert_eq!(rank_with_replacement(3, &[2, 1]), None);
+    assert_eq!(rank_with_replacement(0, &[]), Some(0));
+
+    // n + k - 1 does not fit in a usize
+    assert_eq!(count_with_replacement(10, usize::MAX), None);
+    assert_eq!(count_with_replacement(1, usize::MAX), Some(1));
+    assert_eq!(unrank_with_replacement(10, usize::MAX, 0), None);
+    assert_eq!(rank_with_replacement(usize::MAX, &[0, 0]), None);
+    assert_eq!(CombinationsWithReplacement::new(&items, usize::MAX).next(), None);
+    assert_eq!(CombinationsWithReplacement::from_rank(&items, usize::MAX, 0).next(), None);
+}
@@ -0,0 +1,215 @@
+use crate::remaining::Remaining;
+use crate::wide_binomial;
+use std::iter::FusedIterator;
+
+/// The indices `(removed, added)` of the items that left and entered a combination since the previous one.
+pub type Swap = (usize, usize);
+
+/// Iterates over all possible combinations of items in revolving-door order, where each combination differs from the
+/// previous one by exactly one item leaving and one item entering.
+/// 
+/// Along with each combination, the iterator produces the indices into `items` of the item that was removed and the
+/// item that was added since the previous combination, as a [`Swap`], or `None` for the first combination.
+/// This makes it cheap to keep running totals or scores over the combinations up to date. The order is that of
+/// Knuth's Algorithm R (The Art of Computer Programming, section 7.2.1.3).
+/// 
+/// # Examples
+/// 
+/// ```
+/// use gen_combinations::RevolvingDoor;
+/// 
+/// let items = [1, 2, 3, 4];
+/// let mut sum = 0;
+/// for (combo, swap) in RevolvingDoor::new(&items, 2) {
+///     match swap {
+///         None => sum = combo.iter().copied().sum(),
+///         Some((removed, added)) => sum += items[added] - items[removed],
+///     }
+///     assert_eq!(sum, combo.iter().copied().sum());
+/// }
+/// ```
+/// 
+/// [`Swap`]: type.Swap.html
+#[derive(Debug)]
+pub struct RevolvingDoor<'a, T> {
+    items: &'a [T],
+    indices: Vec<usize>, // the next combination, followed by items.len()
+    swap: Option<Swap>, // the change that led to the next combination
+    remaining: Remaining,
+    done: bool,
+    buffer: Vec<&'a T>, // reused by next_slice
+}
+
+impl<'a, T> RevolvingDoor<'a, T> {
+    /// Creates an iterator over combinations of `items` with length `n` in revolving-door order.
+    /// 
+    /// If `n` is 0 or greater than `items.len()`, the iterator will produce no values.
+    pub fn new(items: &[T], n: usize) -> RevolvingDoor<'_, T> {
+        let done = n == 0 || n > items.len();
+        let mut indices: Vec<usize> = (0..n).collect();
+        indices.push(items.len());
+        let remaining = if done { Remaining::zero() } else { Remaining::new(wide_binomial(items.len(), n)) };
+        RevolvingDoor { items, indices, swap: None, remaining, done, buffer: Vec::new() }
+    }
+
+    /// Returns the number of combinations left, or `None` if it does not fit in a `usize` and [`len`] would panic.
+    /// 
+    /// [`len`]: https://doc.rust-lang.org/std/iter/trait.ExactSizeIterator.html#method.len
+    pub fn remaining(&self) -> Option<usize> {
+        self.remaining.get()
+    }
+
+    /// Returns the indices into `items` of the next combination, or an empty slice if there are no combinations left.
+    pub fn indices(&self) -> &[usize] {
+        if self.done {
+            &[]
+        } else {
+            &self.indices[..self.indices.len() - 1]
+        }
+    }
+
+    /// Advances the iterator and returns the next combination and change, like [`next`], but with the combination in
+    /// a buffer owned by the iterator instead of a newly allocated `Vec`.
+    /// 
+    /// [`next`]: https://doc.rust-lang.org/std/iter/trait.Iterator.html#tymethod.next
+    pub fn next_slice(&mut self) -> Option<(&[&'a T], Option<Swap>)> {
+        if self.done {
+            return None;
+        }
+        let items = self.items;
+        let k = self.indices.len() - 1;
+        self.buffer.clear();
+        self.buffer.extend(self.indices[..k].iter().map(|i| &items[*i]));
+        let swap = self.step();
+        Some((&self.buffer, swap))
+    }
+
+    /// Moves to the next combination, returning the change that led to the current one.
+    fn step(&mut self) -> Option<Swap> {
+        let ret = self.swap;
+        self.remaining = self.remaining.consumed(1, || None);
+        self.swap = revolve(&mut self.indices);
+        if self.swap.is_none() || self.remaining.is_zero() {
+            self.done = true;
+        }
+        ret
+    }
+}
+
+impl<T> Clone for RevolvingDoor<'_, T> {
+    fn clone(&self) -> Self {
+        RevolvingDoor {
+            items: self.items,
+            indices: self.indices.clone(),
+            swap: self.swap,
+            remaining: self.remaining,
+            done: self.done,
+            buffer: Vec::new(),
+        }
+    }
+}
+
+impl<'a, T> Iterator for RevolvingDoor<'a, T> {
+    type Item = (Vec<&'a T>, Option<Swap>);
+
+    fn next(&mut self) -> Option<(Vec<&'a T>, Option<Swap>)> {
+        if self.done {
+            return None;
+        }
+        let combo = self.indices().iter().map(|i| &(self.items[*i])).collect();
+        let swap = self.step();
+        Some((combo, swap))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.remaining.size_hint()
+    }
+}
+
+impl<T> ExactSizeIterator for RevolvingDoor<'_, T> {}
+
+impl<T> FusedIterator for RevolvingDoor<'_, T> {}
+
+/// Moves `c`, a combination followed by the number of items, to the next one in revolving-door order.
+/// 
+/// Returns the indices `(removed, added)`, or `None` if it was the last combination.
+fn revolve(c: &mut [usize]) -> Option<Swap> {
+    // Knuth's Algorithm R, with c_j at c[j - 1]
+    let t = c.len() - 1;
+    if t % 2 == 1 {
+        if c[0] + 1 < c[1] {
+            c[0] += 1;
+            return Some((c[0] - 1, c[0]));
+        }
+    } else if c[0] > 0 {
+        c[0] -= 1;
+        return Some((c[0] + 1, c[0]));
+    }
+    let mut j = 2;
+    // whether to start by trying to decrease c_j, which is the case when c_j = c_(j-1) + 1, or else to increase it
+    let mut decrease = t % 2 == 1;
+    while j <= t {
+        if decrease {
+            if c[j - 1] >= j {
+                let removed = c[j - 1];
+                c[j - 1] = c[j - 2];
+                c[j - 2] = j - 2;
+                return Some((removed, j - 2));
+            }
+            j += 1;
+            decrease = false;
+        } else {
+            if c[j - 1] + 1 < c[j] {
+                c[j - 2] = c[j - 1];
+                c[j - 1] += 1;
+                return Some((j - 2, c[j - 1]));
+            }
+            j += 1;
+            decrease = true;
+        }
+    }
+    None
+}
+
+#[test]
+fn revolving_door_is_a_gray_code() {
+    use std::collections::BTreeSet;
+
+    for n in 1..9 {
+        for k in 1..=n {
+            let items: Vec<usize> = (0..n).collect();
+            let combos: Vec<_> = RevolvingDoor::new(&items, k).collect();
+            assert_eq!(combos.len(), crate::binomial(n, k).unwrap());
+            let distinct: BTreeSet<Vec<usize>> =
+                combos.iter().map(|(combo, _)| combo.iter().copied().copied().collect()).collect();
+            assert_eq!(distinct.len(), combos.len());
+            assert_eq!(combos[0], ((0..k).map(|i| &items[i]).collect(), None));
+            for pair in combos.windows(2) {
+                let before: BTreeSet<usize> = pair[0].0.iter().copied().copied().collect();
+                let after: BTreeSet<usize> = pair[1].0.iter().copied().copied().collect();
+                let (removed, added) = pair[1].1.unwrap();
+                assert!(before.contains(&removed) && !after.contains(&removed));
+                assert!(after.contains(&added) && !before.contains(&added));
+                assert_eq!(before.difference(&after).count(), 1);
+            }
+        }
+    }
+}
+
+#[test]
+fn revolving_door_order() {
+    let items = [0, 1, 2, 3, 4];
+    let mut c = RevolvingDoor::new(&items, 3);
+    let mut order = Vec::new();
+    while let Some((combo, _)) = c.next_slice() {
+        order.push(combo.iter().copied().copied().collect::<Vec<_>>());
+    }
+    // Knuth's table for (s, t) = (2, 3), written with the largest item last
+    let expected = [
+        [0, 1, 2], [0, 2, 3], [1, 2, 3], [0, 1, 3], [0, 3, 4],
+        [1, 3, 4], [2, 3, 4], [0, 2, 4], [1, 2, 4], [0, 1, 4],
+    ];
+    assert_eq!(order, expected);
+    assert_eq!(c.len(), 0);
+    assert_eq!(RevolvingDoor::new(&items, 0).next(), None);
+}
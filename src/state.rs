@@ -0,0 +1,190 @@
+use crate::cursor::Cursor;
+use crate::{CombinationIterator, Order};
+
+/// The position of a [`CombinationIterator`], saved apart from its items so that the iteration can be picked up again
+/// later, such as after a long-running job is restarted.
+/// 
+/// [`CombinationIterator::state`] saves it and [`CombinationIterator::resume`] continues from it. It records the
+/// number of items, the length of the combinations, the next combination from each end and the order, or that there
+/// are no combinations left. With the `serde` feature, it implements `Serialize` and `Deserialize`, so it can be
+/// written to a checkpoint in any format serde supports.
+/// 
+/// # Examples
+/// 
+/// ```
+/// use gen_combinations::CombinationIterator;
+/// 
+/// let items = ['a', 'b', 'c', 'd'];
+/// let mut c = CombinationIterator::new(&items, 2);
+/// c.nth(2);
+/// let state = c.state();
+/// assert_eq!(state.indices(), &[1, 2]);
+/// 
+/// let resumed = CombinationIterator::resume(&items, &state).unwrap();
+/// assert_eq!(resumed.collect::<Vec<_>>(), c.collect::<Vec<_>>());
+/// ```
+/// 
+/// [`CombinationIterator`]: struct.CombinationIterator.html
+/// [`CombinationIterator::state`]: struct.CombinationIterator.html#method.state
+/// [`CombinationIterator::resume`]: struct.CombinationIterator.html#method.resume
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CombinationState {
+    n: usize,
+    k: usize,
+    front: Vec<usize>,
+    back: Vec<usize>,
+    order: Order,
+    exhausted: bool,
+}
+
+impl CombinationState {
+    /// Returns the number of items the iterator was over.
+    pub fn n(&self) -> usize {
+        self.n
+    }
+
+    /// Returns the length of the combinations.
+    pub fn k(&self) -> usize {
+        self.k
+    }
+
+    /// Returns the indices of the next combination from the front, or an empty slice if there were no combinations
+    /// left.
+    pub fn indices(&self) -> &[usize] {
+        &self.front
+    }
+
+    /// Returns the order in which the combinations are produced.
+    pub fn order(&self) -> Order {
+        self.order
+    }
+
+    /// Returns `true` if there were no combinations left.
+    pub fn is_exhausted(&self) -> bool {
+        self.exhausted
+    }
+}
+
+impl<'a, T> CombinationIterator<'a, T> {
+    /// Saves the position of the iterator, without its items, so it can be resumed with [`resume`].
+    /// 
+    /// [`resume`]: #method.resume
+    pub fn state(&self) -> CombinationState {
+        CombinationState {
+            n: self.items.len(),
+            k: self.cursor.k(),
+            front: self.cursor.front().to_vec(),
+            back: self.cursor.back().to_vec(),
+            order: self.cursor.order(),
+            exhausted: self.cursor.is_empty(),
+        }
+    }
+
+    /// Creates an iterator over combinations of `items` that continues from a position saved by [`state`], producing
+    /// the same combinations that the saved iterator had left.
+    /// 
+    /// Returns `None` if the state was saved from an iterator over a different number of items, or is not a position
+    /// that any iterator could have been in, such as when a combination in it is not strictly increasing.
+    /// 
+    /// # Examples
+    /// 
+    /// ```
+    /// use gen_combinations::CombinationIterator;
+    /// 
+    /// let items = [1, 2, 3, 4, 5];
+    /// let state = CombinationIterator::from_rank(&items, 3, 7).state();
+    /// assert_eq!(CombinationIterator::resume(&items, &state).unwrap().len(), 3);
+    /// assert!(CombinationIterator::resume(&items[..4], &state).is_none());
+    /// ```
+    /// 
+    /// [`state`]: #method.state
+    pub fn resume(items: &'a [T], state: &CombinationState) -> Option<CombinationIterator<'a, T>> {
+        let n = items.len();
+        let valid = |indices: &[usize]| {
+            indices.len() == state.k
+                && indices.windows(2).all(|w| w[0] < w[1])
+                && indices.last().is_some_and(|&i| i < n)
+        };
+        if state.n != n {
+            return None;
+        }
+        let cursor = if state.exhausted {
+            if !state.front.is_empty() || !state.back.is_empty() {
+                return None;
+            }
+            Cursor::empty(n, state.k, state.order)
+        } else {
+            if !valid(&state.front) || !valid(&state.back) {
+                return None;
+            }
+            let cursor = Cursor::between(n, state.front.clone(), state.back.clone(), state.order);
+            if cursor.is_empty() {
+                // the back comes before the front
+                return None;
+            }
+            cursor
+        };
+        Some(CombinationIterator { items, cursor, buffer: Vec::new() })
+    }
+}
+
+#[test]
+fn resume_every_position() {
+    let items: Vec<usize> = (0..7).collect();
+    for &order in &[Order::Lexicographic, Order::Colexicographic] {
+        let all: Vec<_> = CombinationIterator::with_order(&items, 3, order).collect();
+        for front in 0..=all.len() {
+            for back in 0..=all.len() - front {
+                let mut c = CombinationIterator::with_order(&items, 3, order);
+                if front > 0 {
+                    c.nth(front - 1);
+                }
+                if back > 0 {
+                    c.nth_back(back - 1);
+                }
+                let state = c.state();
+                assert_eq!(state.is_exhausted(), front + back == all.len());
+                assert_eq!((state.n(), state.k(), state.order()), (7, 3, order));
+                let resumed = CombinationIterator::resume(&items, &state).unwrap();
+                assert_eq!(resumed.len(), c.len());
+                assert!(resumed.eq(all[front..all.len() - back].iter().cloned()));
+            }
+        }
+    }
+    let shard = CombinationIterator::new(&items, 3).shard(1, 3);
+    assert!(CombinationIterator::resume(&items, &shard.state()).unwrap().eq(shard));
+    let empty = CombinationIterator::from_rank(&items, 3, 35).state();
+    assert_eq!((empty.k(), empty.is_exhausted()), (3, true));
+}
+
+#[test]
+fn resume_rejects_invalid_states() {
+    let items: Vec<usize> = (0..7).collect();
+    let state = CombinationIterator::from_rank(&items, 3, 4).state();
+    assert!(CombinationIterator::resume(&items[..6], &state).is_none());
+    let edit = |f: &dyn Fn(&mut CombinationState)| {
+        let mut state = state.clone();
+        f(&mut state);
+        CombinationIterator::resume(&items, &state).is_none()
+    };
+    assert!(edit(&|s| s.front = vec![0, 2, 1]));
+    assert!(edit(&|s| s.front = vec![0, 1]));
+    assert!(edit(&|s| s.back = vec![0, 1, 7]));
+    assert!(edit(&|s| s.back = vec![0, 1, 2]));
+    assert!(edit(&|s| s.k = 2));
+    assert!(edit(&|s| s.exhausted = true));
+    assert!(!edit(&|s| s.back = s.front.clone()));
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn serde_round_trip() {
+    let items: Vec<usize> = (0..40).collect();
+    let mut c = CombinationIterator::with_order(&items, 5, Order::Colexicographic).shard(3, 4);
+    c.nth(1000);
+    let json = serde_json::to_string(&c.state()).unwrap();
+    let state: CombinationState = serde_json::from_str(&json).unwrap();
+    assert_eq!(state, c.state());
+    assert!(CombinationIterator::resume(&items, &state).unwrap().eq(c));
+}
@@ -0,0 +1,531 @@
+use crate::cursor::successor;
+use crate::remaining::{Ranked, Remaining};
+use crate::{narrow, unrank_into, wide_binomial, wide_rank};
+use std::iter::FusedIterator;
+use std::ops::{Bound, RangeBounds};
+
+/// An order in which to produce subsets.
+/// 
+/// Both orders compare subsets by the increasing indices of their items.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum SubsetOrder {
+    /// Subsets are ordered by size, and subsets of the same size in lexicographic order. This is the default.
+    #[default]
+    BySize,
+    /// Subsets are ordered as binary numbers, where item `i` is bit `i`: the subset `{0}` comes first, then `{1}`,
+    /// then `{0, 1}`, then `{2}`, and so on, skipping any subsets of the wrong size.
+    Binary,
+}
+
+impl SubsetOrder {
+    /// Returns the rank of a subset in this order among the subsets of `n` items with sizes in `sizes`, given as the
+    /// strictly increasing `indices` of its items.
+    /// 
+    /// The first subset has rank 0. Returns `None` if `indices` is not strictly increasing, if any index is not less
+    /// than `n`, if its size is not in `sizes`, or if the rank does not fit in a `usize`.
+    /// 
+    /// # Examples
+    /// 
+    /// ```
+    /// use gen_combinations::SubsetOrder;
+    /// 
+    /// assert_eq!(SubsetOrder::BySize.rank(4, 1..=2, &[0, 1]), Some(4));
+    /// assert_eq!(SubsetOrder::Binary.rank(4, 1..=2, &[0, 1]), Some(2));
+    /// ```
+    pub fn rank<R: RangeBounds<usize>>(self, n: usize, sizes: R, indices: &[usize]) -> Option<usize> {
+        let (lo, hi) = bounds(n, &sizes)?;
+        if indices.windows(2).any(|w| w[0] >= w[1]) || indices.last().is_some_and(|&i| i >= n) {
+            return None;
+        }
+        if indices.len() < lo || indices.len() > hi {
+            return None;
+        }
+        narrow(wide_subset_rank(self, n, (lo, hi), indices)?)
+    }
+
+    /// Returns the indices of the subset of `n` items with sizes in `sizes` that has the given `rank` in this order.
+    /// 
+    /// This is the inverse of [`rank`]. Returns `None` if there are not more than `rank` such subsets.
+    /// 
+    /// # Examples
+    /// 
+    /// ```
+    /// use gen_combinations::SubsetOrder;
+    /// 
+    /// assert_eq!(SubsetOrder::BySize.unrank(4, 1..=2, 4), Some(vec![0, 1]));
+    /// assert_eq!(SubsetOrder::Binary.unrank(4, 1..=2, 2), Some(vec![0, 1]));
+    /// ```
+    /// 
+    /// [`rank`]: #method.rank
+    pub fn unrank<R: RangeBounds<usize>>(self, n: usize, sizes: R, rank: usize) -> Option<Vec<usize>> {
+        subset_unrank(self, n, bounds(n, &sizes)?, rank as u128)
+    }
+}
+
+/// Iterates over all subsets of items whose sizes fall in a range.
+/// 
+/// Unlike [`CombinationIterator`], this produces the empty subset if the range includes 0, so an unbounded range
+/// produces the whole power set. The subsets are produced in the given [`SubsetOrder`], by size by default, and the
+/// iterator always knows how many it has left, in the same way as [`CombinationIterator`].
+/// 
+/// # Examples
+/// 
+/// ```
+/// use gen_combinations::Subsets;
+/// 
+/// let items = [1, 2, 3];
+/// for subset in Subsets::new(&items, 1..) {
+///     println!("{:?}", subset);
+///     // [1]
+///     // [2]
+///     // [3]
+///     // [1, 2]
+///     // [1, 3]
+///     // [2, 3]
+///     // [1, 2, 3]
+/// }
+/// ```
+/// 
+/// [`CombinationIterator`]: struct.CombinationIterator.html
+/// [`SubsetOrder`]: enum.SubsetOrder.html
+#[derive(Debug)]
+pub struct Subsets<'a, T> {
+    items: &'a [T],
+    sizes: (usize, usize), // the smallest and largest sizes
+    order: SubsetOrder,
+    front: Vec<usize>, // the next subset
+    remaining: Remaining,
+    buffer: Vec<&'a T>, // reused by next_slice
+}
+
+impl<'a, T> Subsets<'a, T> {
+    /// Creates an iterator over the subsets of `items` with sizes in `sizes`, ordered by size.
+    /// 
+    /// If no sizes from 0 to `items.len()` are in `sizes`, the iterator will produce no values.
+    pub fn new<R: RangeBounds<usize>>(items: &'a [T], sizes: R) -> Subsets<'a, T> {
+        Subsets::with_order(items, sizes, SubsetOrder::BySize)
+    }
+
+    /// Creates an iterator over the subsets of `items` with sizes in `sizes` in the given order.
+    /// 
+    /// # Examples
+    /// 
+    /// ```
+    /// use gen_combinations::{SubsetOrder, Subsets};
+    /// 
+    /// let items = ['a', 'b', 'c'];
+    /// let binary: Vec<_> = Subsets::with_order(&items, ..=2, SubsetOrder::Binary).collect();
+    /// assert_eq!(binary, [&[][..], &[&'a'], &[&'b'], &[&'a', &'b'], &[&'c'], &[&'a', &'c'], &[&'b', &'c']]);
+    /// ```
+    pub fn with_order<R: RangeBounds<usize>>(items: &'a [T], sizes: R, order: SubsetOrder) -> Subsets<'a, T> {
+        let n = items.len();
+        match bounds(n, &sizes) {
+            Some((lo, hi)) => Subsets {
+                items,
+                sizes: (lo, hi),
+                order,
+                front: (0..lo).collect(),
+                remaining: Remaining::new(wide_count(n, lo, hi)),
+                buffer: Vec::new(),
+            },
+            None => Subsets {
+                items,
+                sizes: (0, 0),
+                order,
+                front: Vec::new(),
+                remaining: Remaining::zero(),
+                buffer: Vec::new(),
+            },
+        }
+    }
+
+    /// Returns the order in which the subsets are produced.
+    pub fn order(&self) -> SubsetOrder {
+        self.order
+    }
+
+    /// Returns the number of subsets left, or `None` if it does not fit in a `usize` and [`len`] would panic.
+    /// 
+    /// [`len`]: https://doc.rust-lang.org/std/iter/trait.ExactSizeIterator.html#method.len
+    pub fn remaining(&self) -> Option<usize> {
+        self.remaining.get()
+    }
+
+    /// Returns the indices into `items` of the next subset, or `None` if there are no subsets left.
+    pub fn indices(&self) -> Option<&[usize]> {
+        if self.remaining.is_zero() {
+            None
+        } else {
+            Some(&self.front)
+        }
+    }
+
+    /// Advances the iterator and returns the next subset, like [`next`], but in a buffer owned by the iterator instead
+    /// of a newly allocated `Vec`.
+    /// 
+    /// [`next`]: https://doc.rust-lang.org/std/iter/trait.Iterator.html#tymethod.next
+    pub fn next_slice(&mut self) -> Option<&[&'a T]> {
+        let mut buffer = std::mem::take(&mut self.buffer);
+        let more = self.next_into(&mut buffer);
+        self.buffer = buffer;
+        if more {
+            Some(&self.buffer)
+        } else {
+            None
+        }
+    }
+
+    /// Advances the iterator and writes the next subset into `subset`, replacing its contents.
+    /// 
+    /// Returns `false`, leaving `subset` unchanged, if there are no subsets left.
+    pub fn next_into(&mut self, subset: &mut Vec<&'a T>) -> bool {
+        if self.remaining.is_zero() {
+            return false;
+        }
+        let items = self.items;
+        subset.clear();
+        subset.extend(self.front.iter().map(|i| &items[*i]));
+        self.step();
+        true
+    }
+
+    /// Updates the remaining count after moving forward by `by` subsets.
+    fn consume(&mut self, by: usize) {
+        self.remaining = self.remaining.consumed(by, || {
+            let n = self.items.len();
+            let (lo, hi) = self.sizes;
+            Some(wide_count(n, lo, hi)? - wide_subset_rank(self.order, n, self.sizes, &self.front)?)
+        });
+    }
+}
+
+impl<T> Ranked for Subsets<'_, T> {
+    fn left(&self) -> Remaining {
+        self.remaining
+    }
+
+    fn rank(&self) -> Option<u128> {
+        wide_subset_rank(self.order, self.items.len(), self.sizes, &self.front)
+    }
+
+    fn jump(&mut self, rank: u128, by: usize) {
+        match subset_unrank(self.order, self.items.len(), self.sizes, rank) {
+            Some(front) => {
+                self.front = front;
+                self.consume(by);
+            }
+            None => self.exhaust(),
+        }
+    }
+
+    fn step(&mut self) {
+        let n = self.items.len();
+        let (lo, hi) = self.sizes;
+        let more = !self.remaining.is_one()
+            && match self.order {
+                SubsetOrder::BySize => by_size_successor(&mut self.front, n, hi),
+                SubsetOrder::Binary => binary_successor(&mut self.front, n, lo, hi),
+            };
+        if more {
+            self.consume(1);
+        } else {
+            self.exhaust();
+        }
+    }
+
+    fn exhaust(&mut self) {
+        self.front.clear();
+        self.remaining = Remaining::zero();
+    }
+}
+
+impl<T> Clone for Subsets<'_, T> {
+    fn clone(&self) -> Self {
+        Subsets {
+            items: self.items,
+            sizes: self.sizes,
+            order: self.order,
+            front: self.front.clone(),
+            remaining: self.remaining,
+            buffer: Vec::new(),
+        }
+    }
+}
+
+impl<'a, T> Iterator for Subsets<'a, T> {
+    type Item = Vec<&'a T>;
+
+    fn next(&mut self) -> Option<Vec<&'a T>> {
+        if self.remaining.is_zero() {
+            return None;
+        }
+        let ret = self.front.iter().map(|i| &self.items[*i]).collect();
+        self.step();
+        Some(ret)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.remaining.size_hint()
+    }
+
+    fn count(self) -> usize {
+        self.remaining.get().expect("more than usize::MAX subsets")
+    }
+
+    fn nth(&mut self, n: usize) -> Option<Vec<&'a T>> {
+        if self.skip_ahead(n) {
+            self.next()
+        } else {
+            None
+        }
+    }
+}
+
+impl<T> ExactSizeIterator for Subsets<'_, T> {}
+
+impl<T> FusedIterator for Subsets<'_, T> {}
+
+/// Returns the number of subsets of `n` items with sizes in `sizes`, or `None` if it does not fit in a `usize`.
+/// 
+/// # Examples
+/// 
+/// ```
+/// assert_eq!(gen_combinations::count_subsets(5, 2..=3), Some(20));
+/// assert_eq!(gen_combinations::count_subsets(5, ..), Some(32));
+/// ```
+pub fn count_subsets<R: RangeBounds<usize>>(n: usize, sizes: R) -> Option<usize> {
+    match bounds(n, &sizes) {
+        Some((lo, hi)) => narrow(wide_count(n, lo, hi)?),
+        None => Some(0),
+    }
+}
+
+/// Returns the smallest and largest sizes from 0 to `n` in `sizes`, or `None` if there are none.
+pub(crate) fn bounds<R: RangeBounds<usize>>(n: usize, sizes: &R) -> Option<(usize, usize)> {
+    let lo = match sizes.start_bound() {
+        Bound::Included(&lo) => lo,
+        Bound::Excluded(&lo) => lo.checked_add(1)?,
+        Bound::Unbounded => 0,
+    };
+    let hi = match sizes.end_bound() {
+        Bound::Included(&hi) => hi,
+        Bound::Excluded(&hi) => hi.checked_sub(1)?,
+        Bound::Unbounded => n,
+    };
+    let hi = hi.min(n);
+    if lo > hi {
+        None
+    } else {
+        Some((lo, hi))
+    }
+}
+
+/// Returns the number of subsets of `n` items with sizes from `lo` to `hi`, or `None` if it does not fit in a `u128`.
+fn wide_count(n: usize, lo: usize, hi: usize) -> Option<u128> {
+    if lo == 0 && hi >= n {
+        return if n < 128 { Some(1 << n) } else { None };
+    }
+    (lo..=hi.min(n)).try_fold(0u128, |count, k| count.checked_add(wide_binomial(n, k)?))
+}
+
+/// Returns the rank of the strictly increasing `indices` among the subsets with sizes from `lo` to `hi`, or `None` if
+/// it does not fit in a `u128`.
+fn wide_subset_rank(order: SubsetOrder, n: usize, (lo, hi): (usize, usize), indices: &[usize]) -> Option<u128> {
+    let k = indices.len();
+    match order {
+        SubsetOrder::BySize => {
+            let smaller = if k > lo { wide_count(n, lo, k - 1)? } else { 0 };
+            smaller.checked_add(wide_rank(n, indices)?)
+        }
+        SubsetOrder::Binary => {
+            // for each item, the subsets that agree on the larger items but leave it out come first
+            indices.iter().enumerate().try_fold(0u128, |rank, (i, &c)| {
+                let above = k - 1 - i;
+                let below = if above > hi { 0 } else { wide_count(c, lo.saturating_sub(above), hi - above)? };
+                rank.checked_add(below)
+            })
+        }
+    }
+}
+
+/// Returns the subset with sizes from `lo` to `hi` that has the given rank, or `None` if there is none.
+fn subset_unrank(order: SubsetOrder, n: usize, (lo, hi): (usize, usize), mut rank: u128) -> Option<Vec<usize>> {
+    if wide_count(n, lo, hi).is_some_and(|count| rank >= count) {
+        return None;
+    }
+    match order {
+        SubsetOrder::BySize => {
+            for k in lo..=hi {
+                match wide_binomial(n, k) {
+                    Some(count) if rank >= count => rank -= count,
+                    _ => {
+                        let mut indices = vec![0; k];
+                        unrank_into(&mut indices, 0, n, rank);
+                        return Some(indices);
+                    }
+                }
+            }
+            None
+        }
+        SubsetOrder::Binary => {
+            // decide each item from the largest down, including it if the subsets without it are not enough
+            let mut indices = Vec::new();
+            for c in (0..n).rev() {
+                let above = indices.len();
+                let without = if above > hi { Some(0) } else { wide_count(c, lo.saturating_sub(above), hi - above) };
+                if let Some(without) = without.filter(|&without| rank >= without) {
+                    rank -= without;
+                    indices.push(c);
+                }
+            }
+            indices.reverse();
+            Some(indices)
+        }
+    }
+}
+
+/// Moves `indices` to the next subset of `n` items ordered by size, up to size `hi`.
+/// 
+/// Returns `false` if it is already the last subset.
+fn by_size_successor(indices: &mut Vec<usize>, n: usize, hi: usize) -> bool {
+    if successor(indices, n) {
+        return true;
+    }
+    let k = indices.len() + 1;
+    if k > hi {
+        return false;
+    }
+    indices.clear();
+    indices.extend(0..k);
+    true
+}
+
+/// Moves `indices` to the next subset of `n` items with a size from `lo` to `hi` in binary order.
+/// 
+/// Returns `false` if it is already the last subset.
+fn binary_successor(indices: &mut Vec<usize>, n: usize, lo: usize, hi: usize) -> bool {
+    // add one, then keep adding the lowest set bit until there are few enough bits set
+    if indices.first() != Some(&0) {
+        if n == 0 {
+            return false;
+        }
+        indices.insert(0, 0);
+    } else if !carry(indices, n) {
+        return false;
+    }
+    while indices.len() > hi {
+        if !carry(indices, n) {
+            return false;
+        }
+    }
+    // then set the lowest unset bits until there are enough
+    let mut missing = lo.saturating_sub(indices.len());
+    if missing > 0 {
+        let mut filled = Vec::with_capacity(lo);
+        let mut next = 0;
+        for &c in indices.iter() {
+            while missing > 0 && next < c {
+                filled.push(next);
+                next += 1;
+                missing -= 1;
+            }
+            filled.push(c);
+            next = c + 1;
+        }
+        filled.extend(next..next + missing);
+        *indices = filled;
+    }
+    true
+}
+
+/// Adds the lowest set bit of the nonempty subset `indices` of `n` items to it as a binary number.
+/// 
+/// Returns `false`, leaving `indices` unchanged, if the result would not fit in `n` bits.
+fn carry(indices: &mut Vec<usize>, n: usize) -> bool {
+    let first = indices[0];
+    let run = indices.iter().enumerate().take_while(|&(i, &c)| c == first + i).count();
+    if first + run >= n {
+        return false;
+    }
+    indices.drain(..run - 1);
+    indices[0] = first + run;
+    true
+}
+
+#[test]
+fn subsets_by_size() {
+    use crate::CombinationIterator;
+
+    let items = [1, 2, 3, 4, 5];
+    let mut expected: Vec<Vec<&i32>> = Vec::new();
+    for k in 2..=4 {
+        expected.extend(CombinationIterator::new(&items, k));
+    }
+    let subsets: Vec<_> = Subsets::new(&items, 2..5).collect();
+    assert_eq!(subsets, expected);
+    assert_eq!(Subsets::new(&items, 2..=4).len(), expected.len());
+    assert_eq!(count_subsets(5, 2..=4), Some(expected.len()));
+
+    let everything: Vec<_> = Subsets::new(&items, ..).collect();
+    assert_eq!(everything.len(), 32);
+    assert_eq!(everything[0], Vec::<&i32>::new());
+    assert_eq!(everything[31], items.iter().collect::<Vec<_>>());
+
+    assert_eq!(Subsets::new(&items, 6..).next(), None);
+    assert_eq!(Subsets::new(&items, 3..3).next(), None);
+    assert_eq!(Subsets::new(&[] as &[i32], ..).collect::<Vec<_>>(), [Vec::<&i32>::new()]);
+    assert_eq!(Subsets::with_order(&[] as &[i32], .., SubsetOrder::Binary).count(), 1);
+}
+
+#[test]
+fn subsets_in_binary_order() {
+    for n in 0..7 {
+        let items: Vec<usize> = (0..n).collect();
+        for lo in 0..=n {
+            for hi in lo..=n {
+                let expected: Vec<Vec<usize>> = (0..1usize << n)
+                    .filter(|m| (lo..=hi).contains(&(m.count_ones() as usize)))
+                    .map(|m| (0..n).filter(|i| m & 1 << i != 0).collect())
+                    .collect();
+                let mut subsets = Subsets::with_order(&items, lo..=hi, SubsetOrder::Binary);
+                assert_eq!(subsets.len(), expected.len());
+                let mut lent = Vec::new();
+                while let Some(subset) = subsets.next_slice() {
+                    lent.push(subset.iter().copied().copied().collect::<Vec<_>>());
+                }
+                assert_eq!(lent, expected);
+            }
+        }
+    }
+}
+
+#[test]
+fn subset_rank_and_unrank() {
+    let items: Vec<usize> = (0..6).collect();
+    for &order in &[SubsetOrder::BySize, SubsetOrder::Binary] {
+        let all: Vec<Vec<usize>> =
+            Subsets::with_order(&items, 1..=4, order).map(|s| s.into_iter().copied().collect()).collect();
+        for (r, indices) in all.iter().enumerate() {
+            assert_eq!(order.rank(6, 1..=4, indices), Some(r));
+            assert_eq!(order.unrank(6, 1..=4, r).as_ref(), Some(indices));
+            let mut subsets = Subsets::with_order(&items, 1..=4, order);
+            assert_eq!(subsets.nth(r).map(|s| s.into_iter().copied().collect()).as_ref(), Some(indices));
+            assert_eq!(subsets.len(), all.len() - r - 1);
+        }
+        assert_eq!(order.unrank(6, 1..=4, all.len()), None);
+        assert_eq!(order.rank(6, 1..=4, &[0, 1, 2, 3, 4]), None);
+        assert_eq!(order.rank(6, 1..=4, &[2, 1]), None);
+    }
+}
+
+#[test]
+fn subsets_huge() {
+    let items = vec![(); 200];
+    let mut subsets = Subsets::with_order(&items, .., SubsetOrder::Binary);
+    assert_eq!(subsets.size_hint(), (usize::MAX, None));
+    assert_eq!(subsets.nth(5).map(|s| s.len()), Some(2));
+    assert_eq!(subsets.indices(), Some(&[1, 2][..]));
+    assert_eq!(SubsetOrder::Binary.unrank(200, .., 6), Some(vec![1, 2]));
+    assert_eq!(SubsetOrder::BySize.rank(200, ..=1, &[199]), Some(200));
+    assert_eq!(SubsetOrder::BySize.rank(200, .., &[198, 199]), Some(1 + 200 + 200 * 199 / 2 - 1));
+}